@@ -1,9 +1,67 @@
-use std::io::Cursor;
+use std::{fs, io, io::Cursor, path::PathBuf};
 
 use bevy::{prelude::*, window::WindowId, winit::WinitWindows};
-use image;
+use my_game::storage::{MemoryStorage, Storage, StorageBackend};
 use winit::window::Icon;
 
+/// Saves data as files in the platform's config directory.
+struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    /// Storage in the config directory, or `None` if the platform doesn't
+    /// have one.
+    fn new() -> Option<Self> {
+        Some(Self {
+            dir: config_dir()?.join("guess-hue"),
+        })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.ron", key))
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn config_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join("Library/Application Support"))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            let home = std::env::var_os("HOME")?;
+            Some(PathBuf::from(home).join(".config"))
+        })
+}
+
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.path(key)).ok()
+    }
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(key), value)
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
 fn set_window_icon(windows: NonSend<WinitWindows>) {
     let primary = windows.get_window(WindowId::primary()).unwrap();
 
@@ -27,6 +85,15 @@ fn main() {
     let mut app = my_game::app();
 
     info!("Starting launcher: Native");
+    let storage = match FileStorage::new() {
+        Some(storage) => StorageBackend::new(storage),
+        None => {
+            // NOTE: files would otherwise end up in the working directory
+            warn!("No config directory found, progress won't be saved");
+            StorageBackend::new(MemoryStorage::default())
+        }
+    };
+    app.insert_resource(storage);
     app.add_startup_system(set_window_icon);
     app.run();
 }
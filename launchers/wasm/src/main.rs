@@ -1,10 +1,51 @@
+use std::io;
+
 use bevy::prelude::*;
+use my_game::storage::{Storage, StorageBackend};
 use stylist::yew::styled_component;
 use stylist::{css, global_style};
 use yew::prelude::*;
 
 use my_game::LAUNCHER_TITLE;
 
+const STORAGE_PREFIX: &str = "guess-hue.";
+
+/// Saves data in the browser's `localStorage`.
+struct LocalStorage;
+
+impl LocalStorage {
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok().flatten()
+    }
+}
+
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        let key = format!("{}{}", STORAGE_PREFIX, key);
+        Self::storage()?.get_item(&key).ok().flatten()
+    }
+
+    fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        let key = format!("{}{}", STORAGE_PREFIX, key);
+        let storage = Self::storage().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, "no localStorage")
+        })?;
+        storage.set_item(&key, value).map_err(|err| {
+            io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+        })
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        let key = format!("{}{}", STORAGE_PREFIX, key);
+        let storage = Self::storage().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, "no localStorage")
+        })?;
+        storage.remove_item(&key).map_err(|err| {
+            io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+        })
+    }
+}
+
 fn set_window_title(title: &str) {
     web_sys::window()
         .map(|w| w.document())
@@ -57,5 +98,6 @@ fn main() {
     // Start the Bevy App
     let mut app = my_game::app();
     info!("Starting launcher: WASM");
+    app.insert_resource(StorageBackend::new(LocalStorage));
     app.run();
 }
@@ -0,0 +1,411 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// How the difference between two colors is measured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum ColorMetric {
+    /// Sum of absolute sRGB channel differences. Used to require the exact
+    /// recipe.
+    #[default]
+    Exact,
+    /// Euclidean distance in CIELAB (ΔE*76).
+    Cie76,
+    /// CIEDE2000 (ΔE*00), the most perceptually uniform of the three.
+    Ciede2000,
+    /// Euclidean distance in Oklab.
+    Oklab,
+}
+
+impl ColorMetric {
+    pub fn distance(&self, a: Color, b: Color) -> f32 {
+        let a = srgb(a);
+        let b = srgb(b);
+        let distance = match self {
+            ColorMetric::Exact => {
+                a.iter().zip(b.iter()).map(|(a, b)| f64::abs(a - b)).sum()
+            }
+            ColorMetric::Cie76 => euclidean(srgb_to_lab(a), srgb_to_lab(b)),
+            ColorMetric::Ciede2000 => ciede2000(srgb_to_lab(a), srgb_to_lab(b)),
+            ColorMetric::Oklab => euclidean(srgb_to_oklab(a), srgb_to_oklab(b)),
+        };
+        distance as f32
+    }
+
+    /// Roughly the distance between black and white, past which two colors
+    /// are considered to have nothing in common.
+    fn full_scale(&self) -> f32 {
+        match self {
+            ColorMetric::Exact => 3.0,
+            ColorMetric::Cie76 => 100.0,
+            ColorMetric::Ciede2000 => 100.0,
+            ColorMetric::Oklab => 1.0,
+        }
+    }
+}
+
+/// Decides whether a mix is close enough to a level's objective.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct MatchRule {
+    pub metric: ColorMetric,
+    /// Largest distance, in the metric's units, that is still accepted.
+    #[serde(default)]
+    pub tolerance: f32,
+}
+
+impl Default for MatchRule {
+    fn default() -> Self {
+        Self::strict()
+    }
+}
+
+impl MatchRule {
+    /// Only accepts the exact recipe.
+    pub fn strict() -> Self {
+        Self {
+            metric: ColorMetric::Exact,
+            tolerance: 0.0,
+        }
+    }
+
+    pub fn is_match(&self, a: Color, b: Color) -> bool {
+        let distance = self.metric.distance(a, b);
+        match self.metric {
+            // NOTE: account for small differences in float multiplications
+            ColorMetric::Exact => distance < f32::max(self.tolerance, 0.0001),
+            _ => distance <= self.tolerance,
+        }
+    }
+
+    /// How close two colors are, from 0.0 (nothing in common) to 1.0 (a match).
+    pub fn closeness(&self, a: Color, b: Color) -> f32 {
+        if self.is_match(a, b) {
+            return 1.0;
+        }
+
+        let distance = self.metric.distance(a, b);
+        (1.0 - distance / self.metric.full_scale()).clamp(0.0, 0.99)
+    }
+}
+
+fn srgb(color: Color) -> [f64; 3] {
+    let [r, g, b, _] = color.as_rgba_f32();
+    [r, g, b].map(f64::from)
+}
+
+fn euclidean(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn srgb_to_linear(channel: f64) -> f64 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts sRGB to CIELAB, using the D65 white point.
+pub fn srgb_to_lab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = 0.0193339 * r + 0.1191920 * g + 0.9503041 * b;
+
+    let f = |t: f64| {
+        const DELTA: f64 = 6.0 / 29.0;
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+    let fx = f(x / 0.95047);
+    let fy = f(y);
+    let fz = f(z / 1.08883);
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Converts sRGB to Oklab.
+pub fn srgb_to_oklab(rgb: [f64; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
+    let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
+    let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
+
+    let l = l.cbrt();
+    let m = m.cbrt();
+    let s = s.cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+/// Converts Oklab back to sRGB. Colors outside of the sRGB gamut have
+/// channels outside of `0.0..=1.0`.
+pub fn oklab_to_srgb(lab: [f64; 3]) -> [f64; 3] {
+    let [l, a, b] = lab;
+    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+    .map(linear_to_srgb)
+}
+
+fn linear_to_srgb(channel: f64) -> f64 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// CIEDE2000 color difference between two CIELAB colors.
+pub fn ciede2000(lab1: [f64; 3], lab2: [f64; 3]) -> f64 {
+    let [l1, a1, b1] = lab1;
+    let [l2, a2, b2] = lab2;
+    let c1 = (a1 * a1 + b1 * b1).sqrt();
+    let c2 = (a2 * a2 + b2 * b2).sqrt();
+    let c_mean = (c1 + c2) / 2.0;
+    let c_mean7 = c_mean.powi(7);
+    let g = 0.5 * (1.0 - (c_mean7 / (c_mean7 + 25f64.powi(7))).sqrt());
+
+    let a1 = a1 * (1.0 + g);
+    let a2 = a2 * (1.0 + g);
+    let c1 = (a1 * a1 + b1 * b1).sqrt();
+    let c2 = (a2 * a2 + b2 * b2).sqrt();
+
+    let hue = |a: f64, b: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1 = hue(a1, b1);
+    let h2 = hue(a2, b2);
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 <= h1 {
+        h2 - h1 + 360.0
+    } else {
+        h2 - h1 - 360.0
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let l_mean = (l1 + l2) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_mean - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_mean).to_radians().cos()
+        + 0.32 * (3.0 * h_mean + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_mean - 63.0).to_radians().cos();
+
+    let l_mean_50 = (l_mean - 50.0) * (l_mean - 50.0);
+    let s_l = 1.0 + 0.015 * l_mean_50 / (20.0 + l_mean_50).sqrt();
+    let s_c = 1.0 + 0.045 * c_mean;
+    let s_h = 1.0 + 0.015 * c_mean * t;
+
+    let delta_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let c_mean7 = c_mean.powi(7);
+    let r_c = 2.0 * (c_mean7 / (c_mean7 + 25f64.powi(7))).sqrt();
+    let r_t = -r_c * (2.0 * delta_theta).to_radians().sin();
+
+    let l_term = delta_l / s_l;
+    let c_term = delta_c / s_c;
+    let h_term = delta_h / s_h;
+
+    (l_term * l_term
+        + c_term * c_term
+        + h_term * h_term
+        + r_t * c_term * h_term)
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CIEDE2000 test data from Sharma, Wu and Dalal (2005), "The CIEDE2000
+    /// color-difference formula: implementation notes, supplementary test
+    /// data, and mathematical observations".
+    const SHARMA_PAIRS: [([f64; 3], [f64; 3], f64); 34] = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+        ([50.0, 2.8361, -74.0200], [50.0, 0.0, -82.7485], 3.4412),
+        ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0),
+        ([50.0, -1.1848, -84.8006], [50.0, 0.0, -82.7485], 1.0),
+        ([50.0, -0.9009, -85.5211], [50.0, 0.0, -82.7485], 1.0),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, -1.0, 2.0], [50.0, 0.0, 0.0], 2.3669),
+        // NOTE: pairs 9 to 15 sit on either side of the hue wraparound
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0009], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0010], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0011], 7.2195),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0012], 7.2195),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0009, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0010, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0011, -2.4900], 4.7461),
+        ([50.0, 2.5, 0.0], [50.0, 0.0, -2.5], 4.3065),
+        ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0),
+        ([50.0, 2.5, 0.0], [50.0, 3.2972, 0.0], 1.0),
+        ([50.0, 2.5, 0.0], [50.0, 1.8634, 0.5757], 1.0),
+        ([50.0, 2.5, 0.0], [50.0, 3.2592, 0.3350], 1.0),
+        (
+            [60.2574, -34.0099, 36.2677],
+            [60.4626, -34.1751, 39.4387],
+            1.2644,
+        ),
+        (
+            [63.0109, -31.0961, -5.8663],
+            [62.8187, -29.7946, -4.0864],
+            1.2630,
+        ),
+        (
+            [61.2901, 3.7196, -5.3901],
+            [61.4292, 2.2480, -4.9620],
+            1.8731,
+        ),
+        (
+            [35.0831, -44.1164, 3.7933],
+            [35.0232, -40.0716, 1.5901],
+            1.8645,
+        ),
+        (
+            [22.7233, 20.0904, -46.6940],
+            [23.0331, 14.9730, -42.5619],
+            2.0373,
+        ),
+        (
+            [36.4612, 47.8580, 18.3852],
+            [36.2715, 50.5065, 21.2231],
+            1.4146,
+        ),
+        (
+            [90.8027, -2.0831, 1.4410],
+            [91.1528, -1.6435, 0.0447],
+            1.4441,
+        ),
+        (
+            [90.9257, -0.5406, -0.9208],
+            [88.6381, -0.8985, -0.7239],
+            1.5381,
+        ),
+        (
+            [6.7747, -0.2908, -2.4247],
+            [5.8714, -0.0985, -2.2286],
+            0.6377,
+        ),
+        (
+            [2.0776, 0.0795, -1.1350],
+            [0.9033, -0.0636, -0.5514],
+            0.9082,
+        ),
+    ];
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3], tolerance: f64) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < tolerance,
+                "{:?} is not {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn ciede2000_matches_the_reference_data() {
+        for (index, (lab1, lab2, expected)) in SHARMA_PAIRS.iter().enumerate() {
+            // NOTE: the difference is symmetric, check both orders
+            for distance in [ciede2000(*lab1, *lab2), ciede2000(*lab2, *lab1)] {
+                assert!(
+                    (distance - expected).abs() < 0.0001,
+                    "pair {} is {} apart instead of {}",
+                    index + 1,
+                    distance,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cie76_is_measured_in_cielab() {
+        assert_close(srgb_to_lab([1.0, 1.0, 1.0]), [100.0, 0.0, 0.0], 0.01);
+        assert_close(
+            srgb_to_lab([1.0, 0.0, 0.0]),
+            [53.2408, 80.0925, 67.2032],
+            0.01,
+        );
+
+        let distance = ColorMetric::Cie76.distance(Color::BLACK, Color::WHITE);
+        assert!((distance - 100.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn oklab_matches_the_reference_values() {
+        assert_close(srgb_to_oklab([1.0, 1.0, 1.0]), [1.0, 0.0, 0.0], 0.0001);
+        assert_close(
+            srgb_to_oklab([1.0, 0.0, 0.0]),
+            [0.627955, 0.224863, 0.125846],
+            0.0001,
+        );
+
+        for rgb in [[0.2, 0.5, 0.9], [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]] {
+            assert_close(oklab_to_srgb(srgb_to_oklab(rgb)), rgb, 0.0001);
+        }
+    }
+
+    #[test]
+    fn tolerance_is_the_largest_accepted_distance() {
+        let a = Color::rgb(0.4, 0.5, 0.6);
+        let b = Color::rgb(0.45, 0.5, 0.6);
+        for metric in [ColorMetric::Cie76, ColorMetric::Ciede2000] {
+            let distance = metric.distance(a, b);
+            let rule = |tolerance| MatchRule { metric, tolerance };
+
+            assert!(rule(distance).is_match(a, b));
+            assert_eq!(rule(distance).closeness(a, b), 1.0);
+            assert!(!rule(distance - 0.01).is_match(a, b));
+            assert!(rule(distance - 0.01).closeness(a, b) < 1.0);
+        }
+
+        assert!(MatchRule::strict().is_match(a, a));
+        assert!(!MatchRule::strict().is_match(a, b));
+    }
+}
@@ -1,27 +1,153 @@
-use std::{
-    mem::MaybeUninit,
-    ops::{Add, Mul},
-};
+#[cfg(feature = "cpp-mixbox")]
+use std::mem::MaybeUninit;
+use std::ops::{Add, Mul};
 
 use bevy::prelude::*;
+#[cfg(feature = "cpp-mixbox")]
 use mixbox_sys::{
     mixbox_latent_to_srgb32f, mixbox_srgb32f_to_latent, MIXBOX_NUMLATENTS,
 };
+use serde::Deserialize;
+
+#[cfg(not(any(feature = "cpp-mixbox", feature = "pure-rust-mixbox")))]
+compile_error!(
+    "a mixbox backend is required, enable either the `cpp-mixbox` or the \
+     `pure-rust-mixbox` feature"
+);
+
+/// A way of mixing colors together.
+pub trait MixingModel: Send + Sync {
+    /// Mixes colors in the given amounts. Only the proportions matter, so
+    /// `[(YELLOW, 3.0), (BLUE, 1.0)]` mixes the same as `[(YELLOW, 0.75),
+    /// (BLUE, 0.25)]`.
+    fn mix_weighted(&self, colors: &[(Color, f32)]) -> Color;
+}
+
+/// Selects the [`MixingModel`] used to mix recipes. Used as a resource, set
+/// from the active level pack.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum MixingModelKind {
+    /// Paint-like mixing, where blue and yellow make green.
+    #[default]
+    Mixbox,
+    /// Mixing light, averaged in linear RGB.
+    LinearRgb,
+    /// Averages sRGB values as they are, like most image editors do.
+    Srgb,
+    /// Pigments absorbing light, using Kubelka-Munk on each RGB channel.
+    Subtractive,
+}
+
+impl MixingModelKind {
+    pub fn model(&self) -> &'static dyn MixingModel {
+        match self {
+            MixingModelKind::Mixbox => &Mixbox,
+            MixingModelKind::LinearRgb => &LinearRgb,
+            MixingModelKind::Srgb => &Srgb,
+            MixingModelKind::Subtractive => &Subtractive,
+        }
+    }
+}
+
+pub struct Mixbox;
 
-pub fn mix_colors(colors: &[Color]) -> Color {
-    let weight = 1.0 / colors.len() as f32;
-    colors
-        .iter()
-        .map(|color| Latent::from(color) * weight)
-        .reduce(|accum, item| accum + item)
-        .map_or(Color::NONE, |latent| Color::from(&latent))
+impl MixingModel for Mixbox {
+    fn mix_weighted(&self, colors: &[(Color, f32)]) -> Color {
+        let total = total_amount(colors);
+        if total <= 0.0 {
+            return Color::NONE;
+        }
+
+        colors
+            .iter()
+            .map(|(color, amount)| Latent::from(color) * (amount / total))
+            .reduce(|accum, item| accum + item)
+            .map_or(Color::NONE, |latent| Color::from(&latent))
+    }
+}
+
+pub struct LinearRgb;
+
+impl MixingModel for LinearRgb {
+    fn mix_weighted(&self, colors: &[(Color, f32)]) -> Color {
+        average_channels(colors, |color| color.as_linear_rgba_f32())
+            .map_or(Color::NONE, |[r, g, b]| Color::rgb_linear(r, g, b))
+    }
+}
+
+pub struct Srgb;
+
+impl MixingModel for Srgb {
+    fn mix_weighted(&self, colors: &[(Color, f32)]) -> Color {
+        average_channels(colors, |color| color.as_rgba_f32())
+            .map_or(Color::NONE, |[r, g, b]| Color::rgb(r, g, b))
+    }
+}
+
+pub struct Subtractive;
+
+impl Subtractive {
+    // NOTE: a perfectly white or black channel would absorb nothing or
+    // everything, which the Kubelka-Munk equations can't mix with.
+    const MIN_REFLECTANCE: f32 = 0.001;
+    const MAX_REFLECTANCE: f32 = 0.999;
+
+    /// Ratio of absorption to scattering (K/S) of a channel's reflectance.
+    fn absorption(reflectance: f32) -> f32 {
+        let r = reflectance.clamp(Self::MIN_REFLECTANCE, Self::MAX_REFLECTANCE);
+        (1.0 - r) * (1.0 - r) / (2.0 * r)
+    }
+
+    fn reflectance(absorption: f32) -> f32 {
+        1.0 + absorption - f32::sqrt(absorption * absorption + 2.0 * absorption)
+    }
+}
+
+impl MixingModel for Subtractive {
+    fn mix_weighted(&self, colors: &[(Color, f32)]) -> Color {
+        average_channels(colors, |color| {
+            color.as_linear_rgba_f32().map(Self::absorption)
+        })
+        .map_or(Color::NONE, |absorption| {
+            let [r, g, b] = absorption.map(Self::reflectance);
+            Color::rgb_linear(r, g, b)
+        })
+    }
+}
+
+fn total_amount(colors: &[(Color, f32)]) -> f32 {
+    colors.iter().map(|(_, amount)| amount).sum()
+}
+
+/// Weighted average of the first three channels returned by `channels`, or
+/// `None` when there is nothing to mix.
+fn average_channels(
+    colors: &[(Color, f32)],
+    channels: impl Fn(&Color) -> [f32; 4],
+) -> Option<[f32; 3]> {
+    let total = total_amount(colors);
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut average = [0.0; 3];
+    for (color, amount) in colors {
+        let values = channels(color);
+        for (channel, value) in average.iter_mut().zip(values) {
+            *channel += value * amount / total;
+        }
+    }
+    Some(average)
 }
 
 #[derive(Debug)]
 struct Latent([f32; Latent::LATENT_LENGTH]);
 
 impl Latent {
+    #[cfg(not(feature = "pure-rust-mixbox"))]
     const LATENT_LENGTH: usize = MIXBOX_NUMLATENTS as _;
+    #[cfg(feature = "pure-rust-mixbox")]
+    const LATENT_LENGTH: usize = mixbox::LATENT_SIZE;
 
     pub fn new() -> Self {
         Self([0.0; Latent::LATENT_LENGTH])
@@ -50,36 +176,200 @@ impl Add<Latent> for Latent {
     }
 }
 
+// NOTE: when both backends are enabled, the pure Rust one is used. The C++
+// library is still compiled so that the tests can compare their output.
+#[cfg(not(feature = "pure-rust-mixbox"))]
 impl From<&Latent> for Color {
     fn from(latent: &Latent) -> Self {
+        let [r, g, b] = cpp::latent_to_srgb(&latent.0);
+        Color::rgb(r, g, b)
+    }
+}
+
+#[cfg(not(feature = "pure-rust-mixbox"))]
+impl From<&Color> for Latent {
+    fn from(color: &Color) -> Self {
+        Latent(cpp::srgb_to_latent([color.r(), color.g(), color.b()]))
+    }
+}
+
+#[cfg(feature = "pure-rust-mixbox")]
+impl From<&Latent> for Color {
+    fn from(latent: &Latent) -> Self {
+        let [r, g, b] = mixbox::latent_to_float_rgb(&latent.0);
+        Color::rgb(r, g, b)
+    }
+}
+
+#[cfg(feature = "pure-rust-mixbox")]
+impl From<&Color> for Latent {
+    fn from(color: &Color) -> Self {
+        Latent(mixbox::float_rgb_to_latent(&[
+            color.r(),
+            color.g(),
+            color.b(),
+        ]))
+    }
+}
+
+#[cfg(all(feature = "cpp-mixbox", feature = "pure-rust-mixbox"))]
+const _: () = assert!(
+    MIXBOX_NUMLATENTS as usize == mixbox::LATENT_SIZE,
+    "both mixbox backends must use latents of the same length"
+);
+
+/// Calls into the C++ mixbox library.
+#[cfg(feature = "cpp-mixbox")]
+mod cpp {
+    use super::*;
+
+    pub fn latent_to_srgb(latent: &[f32; Latent::LATENT_LENGTH]) -> [f32; 3] {
         let mut srgb = MaybeUninit::<[f32; 3]>::uninit();
         let srgb_ptr = srgb.as_mut_ptr().cast::<f32>();
 
-        let srgb = unsafe {
+        unsafe {
             mixbox_latent_to_srgb32f(
-                &latent.0 as *const _ as _,
+                latent as *const _ as _,
                 srgb_ptr.offset(0),
                 srgb_ptr.offset(1),
                 srgb_ptr.offset(2),
             );
             srgb.assume_init()
-        };
+        }
+    }
 
-        Color::rgb(srgb[0], srgb[1], srgb[2])
+    pub fn srgb_to_latent([r, g, b]: [f32; 3]) -> [f32; Latent::LATENT_LENGTH] {
+        let mut latent = [0.0; Latent::LATENT_LENGTH];
+        unsafe {
+            mixbox_srgb32f_to_latent(r, g, b, latent.as_mut_ptr());
+        }
+        latent
     }
 }
 
-impl From<&Color> for Latent {
-    fn from(color: &Color) -> Self {
-        let mut result = Latent::new();
-        unsafe {
-            mixbox_srgb32f_to_latent(
-                color.r(),
-                color.g(),
-                color.b(),
-                result.0.as_mut_ptr(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    const MODELS: [MixingModelKind; 4] = [
+        MixingModelKind::Mixbox,
+        MixingModelKind::LinearRgb,
+        MixingModelKind::Srgb,
+        MixingModelKind::Subtractive,
+    ];
+
+    fn assert_channels_close(actual: [f32; 4], expected: [f32; 3]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < EPSILON,
+                "expected {expected:?}, got {actual:?}"
             );
         }
-        result
+    }
+
+    fn luminance(color: Color) -> f32 {
+        let [r, g, b, _] = color.as_linear_rgba_f32();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    #[test]
+    fn linear_rgb_averages_light() {
+        let mix =
+            LinearRgb.mix_weighted(&[(Color::BLACK, 1.0), (Color::WHITE, 1.0)]);
+        assert_channels_close(mix.as_linear_rgba_f32(), [0.5; 3]);
+    }
+
+    #[test]
+    fn srgb_averages_values() {
+        let mix =
+            Srgb.mix_weighted(&[(Color::BLACK, 1.0), (Color::WHITE, 1.0)]);
+        assert_channels_close(mix.as_rgba_f32(), [0.5; 3]);
+    }
+
+    #[test]
+    fn only_proportions_matter() {
+        for kind in MODELS {
+            let model = kind.model();
+            let small =
+                model.mix_weighted(&[(Color::YELLOW, 3.0), (Color::BLUE, 1.0)]);
+            let large = model
+                .mix_weighted(&[(Color::YELLOW, 0.75), (Color::BLUE, 0.25)]);
+            let [r, g, b, _] = small.as_rgba_f32();
+            assert_channels_close(large.as_rgba_f32(), [r, g, b]);
+        }
+    }
+
+    #[test]
+    fn mixing_a_color_with_itself_keeps_it() {
+        let color = Color::rgb(0.8, 0.4, 0.2);
+        for kind in MODELS {
+            let mix = kind.model().mix_weighted(&[(color, 1.0), (color, 2.0)]);
+            assert_channels_close(mix.as_rgba_f32(), [0.8, 0.4, 0.2]);
+        }
+    }
+
+    #[test]
+    fn nothing_to_mix_gives_no_color() {
+        for kind in MODELS {
+            assert_eq!(kind.model().mix_weighted(&[]), Color::NONE);
+        }
+    }
+
+    #[test]
+    fn subtractive_mixes_are_darker_than_light() {
+        let colors = [(Color::YELLOW, 1.0), (Color::BLUE, 1.0)];
+        let paint = Subtractive.mix_weighted(&colors);
+        let light = LinearRgb.mix_weighted(&colors);
+        assert!(luminance(paint) < luminance(light));
+    }
+}
+
+#[cfg(all(test, feature = "cpp-mixbox", feature = "pure-rust-mixbox"))]
+mod backend_tests {
+    use super::*;
+
+    /// Steps of the RGB grid sampled along each channel.
+    const GRID_STEPS: usize = 9;
+    const EPSILON: f32 = 1e-4;
+
+    fn grid() -> impl Iterator<Item = Color> {
+        let step = |i: usize| i as f32 / (GRID_STEPS - 1) as f32;
+        (0..GRID_STEPS).flat_map(move |r| {
+            (0..GRID_STEPS).flat_map(move |g| {
+                (0..GRID_STEPS)
+                    .map(move |b| Color::rgb(step(r), step(g), step(b)))
+            })
+        })
+    }
+
+    fn assert_close(cpp: &[f32], rust: &[f32], color: Color) {
+        for (cpp, rust) in cpp.iter().zip(rust) {
+            assert!(
+                (cpp - rust).abs() < EPSILON,
+                "backends differ for {color:?}: {cpp:?} vs {rust:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn backends_give_the_same_latents() {
+        for color in grid() {
+            let cpp = cpp::srgb_to_latent([color.r(), color.g(), color.b()]);
+            let rust =
+                mixbox::float_rgb_to_latent(&[color.r(), color.g(), color.b()]);
+            assert_close(&cpp, &rust, color);
+        }
+    }
+
+    #[test]
+    fn backends_give_the_same_colors() {
+        for color in grid() {
+            let latent = Latent::from(&color);
+            let cpp = cpp::latent_to_srgb(&latent.0);
+            let rust = mixbox::latent_to_float_rgb(&latent.0);
+            assert_close(&cpp, &rust, color);
+        }
     }
 }
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Simulates protanopia (no red cones) in linear RGB, from Machado, Oliveira
+/// and Fernandes (2009) at full severity.
+pub const PROTANOPIA: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+
+/// Simulates deuteranopia (no green cones), from the same source.
+pub const DEUTERANOPIA: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+
+/// Simulates tritanopia (no blue cones), from the same source.
+pub const TRITANOPIA: [[f32; 3]; 3] = [
+    [1.255528, -0.076749, -0.178779],
+    [-0.078411, 0.930809, 0.147602],
+    [0.004733, 0.691367, 0.303900],
+];
+
+/// How displayed colors are filtered, to preview the game as seen with a
+/// color vision deficiency.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum ColorVision {
+    #[default]
+    Normal,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl ColorVision {
+    pub const ALL: [ColorVision; 4] = [
+        ColorVision::Normal,
+        ColorVision::Deuteranopia,
+        ColorVision::Protanopia,
+        ColorVision::Tritanopia,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorVision::Normal => "Normal",
+            ColorVision::Deuteranopia => "Deuteranopia",
+            ColorVision::Protanopia => "Protanopia",
+            ColorVision::Tritanopia => "Tritanopia",
+        }
+    }
+
+    /// The vision that follows this one in the settings, wrapping around.
+    pub fn next(&self) -> ColorVision {
+        let index = Self::ALL.iter().position(|v| v == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Matrix applied to linear RGB colors, or `None` for normal vision.
+    pub fn matrix(&self) -> Option<&'static [[f32; 3]; 3]> {
+        match self {
+            ColorVision::Normal => None,
+            ColorVision::Deuteranopia => Some(&DEUTERANOPIA),
+            ColorVision::Protanopia => Some(&PROTANOPIA),
+            ColorVision::Tritanopia => Some(&TRITANOPIA),
+        }
+    }
+}
+
+/// Returns how `rgb`, in linear RGB, is seen with `vision`, clamped to the
+/// displayable range.
+pub fn simulate(rgb: [f32; 3], vision: ColorVision) -> [f32; 3] {
+    let matrix = match vision.matrix() {
+        Some(matrix) => matrix,
+        None => return rgb,
+    };
+
+    matrix.map(|row| {
+        let channel: f32 = row.iter().zip(rgb).map(|(m, c)| m * c).sum();
+        channel.clamp(0.0, 1.0)
+    })
+}
+
+/// Returns `color` as it should be displayed for `vision`.
+pub fn simulate_color(color: Color, vision: ColorVision) -> Color {
+    if vision == ColorVision::Normal {
+        return color;
+    }
+
+    let [r, g, b, a] = color.as_linear_rgba_f32();
+    let [r, g, b] = simulate([r, g, b], vision);
+    Color::rgba_linear(r, g, b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    const SAMPLES: [[f32; 3]; 3] =
+        [[0.2, 0.5, 0.8], [0.4, 0.4, 0.4], [0.6, 0.3, 0.2]];
+    const SAMPLE: [f32; 3] = SAMPLES[0];
+
+    // NOTE: cone responses come from an independent model rather than from
+    // the matrices under test: linear RGB to LMS from Viénot, Brettel and
+    // Mollon (1999), and its inverse.
+    const RGB_TO_LMS: [[f32; 3]; 3] = [
+        [17.8824, 43.5161, 4.11935],
+        [3.45565, 27.1554, 3.86714],
+        [0.0299566, 0.184309, 1.46709],
+    ];
+    const LMS_TO_RGB: [[f32; 3]; 3] = [
+        [0.08094445, -0.1305044, 0.1167211],
+        [-0.01024853, 0.05401933, -0.1136147],
+        [-0.0003652969, -0.004121615, 0.6935114],
+    ];
+
+    fn transform(matrix: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+        matrix.map(|row| row.iter().zip(v).map(|(m, c)| m * c).sum())
+    }
+
+    /// `rgb` with the response of one type of cone raised by a tenth.
+    fn shift_cone(rgb: [f32; 3], cone: usize) -> [f32; 3] {
+        let mut lms = transform(&RGB_TO_LMS, rgb);
+        lms[cone] *= 1.1;
+        transform(&LMS_TO_RGB, lms)
+    }
+
+    fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < EPSILON,
+                "expected {expected:?}, got {actual:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn grays_are_unchanged() {
+        for vision in ColorVision::ALL {
+            assert_close(simulate([1.0; 3], vision), [1.0; 3]);
+            assert_close(simulate([0.0; 3], vision), [0.0; 3]);
+        }
+    }
+
+    #[test]
+    fn missing_cones_go_unnoticed() {
+        let missing_cones = [
+            (ColorVision::Protanopia, 0),
+            (ColorVision::Deuteranopia, 1),
+            (ColorVision::Tritanopia, 2),
+        ];
+        for (vision, missing_cone) in missing_cones {
+            for sample in SAMPLES {
+                for cone in 0..3 {
+                    let shifted = shift_cone(sample, cone);
+                    let difference = distance(
+                        simulate(sample, vision),
+                        simulate(shifted, vision),
+                    );
+                    match cone == missing_cone {
+                        true => assert!(
+                            difference < 0.02,
+                            "{vision:?} sees cone {cone} of {sample:?}"
+                        ),
+                        false => assert!(
+                            difference > 0.025,
+                            "{vision:?} misses cone {cone} of {sample:?}"
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_channels_are_clamped() {
+        // NOTE: pure red goes slightly negative on the blue channel
+        let [r, g, b] = simulate([1.0, 0.0, 0.0], ColorVision::Protanopia);
+        assert!((0.0..=1.0).contains(&r) && (0.0..=1.0).contains(&g));
+        assert_eq!(b, 0.0);
+    }
+
+    #[test]
+    fn normal_vision_is_the_identity() {
+        assert_eq!(simulate(SAMPLE, ColorVision::Normal), SAMPLE);
+
+        let color = Color::rgba(0.3, 0.6, 0.9, 0.5);
+        assert_eq!(simulate_color(color, ColorVision::Normal), color);
+    }
+}
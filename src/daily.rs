@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use chrono::{Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color_mixer::MixingModelKind,
+    game::{
+        GameLostEvent, GameMode, GameWonEvent, LevelState, LevelSucceededEvent,
+        RestartRunEvent,
+    },
+    level_generator::LevelGenerator,
+    level_pack::{ActiveLevelPack, LevelPack},
+    save::ResumeRun,
+    AppState, LAUNCHER_TITLE,
+};
+
+/// Number of levels in a daily challenge.
+pub const DAILY_LEVEL_COUNT: u32 = 10;
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::InGame).with_system(setup),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::InGame).with_system(teardown),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(clear_results_on_restart)
+                .with_system(
+                    record_level_results.after(clear_results_on_restart),
+                )
+                .with_system(share_results.after(record_level_results)),
+        );
+    }
+}
+
+/// The date of a daily challenge, which seeds its levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl DailyDate {
+    pub fn today() -> Self {
+        let date = Utc::now().naive_utc().date();
+        Self {
+            year: date.year(),
+            month: date.month(),
+            day: date.day(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.year as u64 * 10_000 + self.month as u64 * 100 + self.day as u64
+    }
+}
+
+impl std::fmt::Display for DailyDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Complexity of the level at `level_index` in a daily challenge. Rises
+/// faster than the campaign so that a short run still gets challenging.
+pub fn level_complexity(level_index: u32) -> usize {
+    2 + (level_index / 2) as usize
+}
+
+/// How a level of a daily challenge went.
+///
+/// Wrong answers cost a life but leave the level to be tried again, so only
+/// the level that the run was lost on counts as failed. A level that cost
+/// lives before being solved still counts as solved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DailyResult {
+    /// Solved without a wrong answer.
+    FirstTry,
+    /// Solved after one or more wrong answers.
+    AfterRetries,
+    /// The run ran out of lives on this level.
+    Failed,
+}
+
+impl DailyResult {
+    fn symbol(&self) -> &'static str {
+        match self {
+            DailyResult::FirstTry => "🟩",
+            DailyResult::AfterRetries => "🟨",
+            DailyResult::Failed => "🟥",
+        }
+    }
+}
+
+/// Results of the daily challenge being played.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DailyRun {
+    pub date: DailyDate,
+    pub results: Vec<DailyResult>,
+}
+
+impl DailyRun {
+    pub fn new(date: DailyDate) -> Self {
+        Self {
+            date,
+            results: default(),
+        }
+    }
+
+    pub fn solved_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| **result != DailyResult::Failed)
+            .count()
+    }
+
+    /// A spoiler-free text summary of the run that can be shared.
+    pub fn summary(&self) -> String {
+        let squares: String = (0..DAILY_LEVEL_COUNT as usize)
+            .map(|index| {
+                self.results
+                    .get(index)
+                    .map_or("⬜", |result| result.symbol())
+            })
+            .collect();
+
+        format!(
+            "{} Daily {} {}/{}\n{}",
+            LAUNCHER_TITLE,
+            self.date,
+            self.solved_count(),
+            DAILY_LEVEL_COUNT,
+            squares
+        )
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mixing: Res<MixingModelKind>,
+    resume: Option<Res<ResumeRun>>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+) {
+    if *mode != GameMode::Daily {
+        return;
+    }
+
+    if let Some(pack) = packs.get(&active_pack.0) {
+        // NOTE: a resumed challenge keeps the date it was started on
+        let run = resume
+            .and_then(|resume| resume.0.daily.clone())
+            .unwrap_or_else(|| DailyRun::new(DailyDate::today()));
+        commands.insert_resource(LevelGenerator::new(
+            run.date.seed(),
+            pack.palette.clone(),
+            *mixing,
+        ));
+        commands.insert_resource(run);
+    }
+}
+
+fn teardown(mut commands: Commands) {
+    commands.remove_resource::<DailyRun>();
+}
+
+fn clear_results_on_restart(
+    run: Option<ResMut<DailyRun>>,
+    mut restart_evr: EventReader<RestartRunEvent>,
+) {
+    if let Some(mut run) = run {
+        if restart_evr.iter().count() > 0 {
+            run.results.clear();
+        }
+    }
+}
+
+fn record_level_results(
+    run: Option<ResMut<DailyRun>>,
+    level: Option<Res<LevelState>>,
+    mut succeeded_evr: EventReader<LevelSucceededEvent>,
+    mut won_evr: EventReader<GameWonEvent>,
+    mut lost_evr: EventReader<GameLostEvent>,
+) {
+    let solved = succeeded_evr.iter().count() + won_evr.iter().count() > 0;
+    let failed = lost_evr.iter().count() > 0;
+
+    if let (Some(mut run), Some(level)) = (run, level) {
+        let result = if failed {
+            DailyResult::Failed
+        } else if !solved {
+            return;
+        } else if level.retries > 0 {
+            DailyResult::AfterRetries
+        } else {
+            DailyResult::FirstTry
+        };
+
+        run.results.push(result);
+    }
+}
+
+fn share_results(
+    run: Option<Res<DailyRun>>,
+    mut won_evr: EventReader<GameWonEvent>,
+    mut lost_evr: EventReader<GameLostEvent>,
+) {
+    let finished = won_evr.iter().count() + lost_evr.iter().count() > 0;
+
+    if let Some(run) = run {
+        if finished {
+            share_summary(&run.summary());
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn share_summary(summary: &str) {
+    info!("Daily challenge results:\n{}", summary);
+
+    let clipboard = web_sys::window().and_then(|w| w.navigator().clipboard());
+    if let Some(clipboard) = clipboard {
+        let _ = clipboard.write_text(summary);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn share_summary(summary: &str) {
+    info!("Daily challenge results:\n{}", summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{level_pack::PaletteColor, recipe::Recipe};
+
+    fn levels(date: DailyDate) -> Vec<Recipe> {
+        let palette = [Color::RED, Color::YELLOW, Color::BLUE, Color::WHITE]
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| PaletteColor {
+                name: format!("Color {}", index),
+                color,
+            })
+            .collect();
+        let mut generator = LevelGenerator::new(
+            date.seed(),
+            palette,
+            MixingModelKind::default(),
+        );
+        (0..DAILY_LEVEL_COUNT)
+            .map(|level_index| {
+                generator
+                    .generate_up_to(level_complexity(level_index))
+                    .expect("the palette has enough colors")
+                    .objective
+            })
+            .collect()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> DailyDate {
+        DailyDate { year, month, day }
+    }
+
+    #[test]
+    fn same_date_plays_the_same_levels() {
+        assert_eq!(levels(date(2026, 3, 14)), levels(date(2026, 3, 14)));
+        assert_ne!(levels(date(2026, 3, 14)), levels(date(2026, 3, 15)));
+        assert_ne!(levels(date(2026, 3, 14)), levels(date(2025, 3, 14)));
+    }
+
+    #[test]
+    fn summary_shows_every_level() {
+        let mut run = DailyRun::new(date(2026, 1, 5));
+        run.results = vec![
+            DailyResult::FirstTry,
+            DailyResult::AfterRetries,
+            DailyResult::FirstTry,
+            DailyResult::Failed,
+        ];
+
+        assert_eq!(
+            run.summary(),
+            format!(
+                "{} Daily 2026-01-05 3/10\n🟩🟨🟩🟥⬜⬜⬜⬜⬜⬜",
+                LAUNCHER_TITLE
+            )
+        );
+    }
+}
@@ -1,6 +1,22 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{color_mixer::mix_colors, AppState};
+use crate::{
+    color_distance::MatchRule,
+    color_mixer::MixingModelKind,
+    daily::{self, DAILY_LEVEL_COUNT},
+    level_generator::LevelGenerator,
+    level_pack::{
+        ActiveLevelPack, BrushAmount, LevelDefinition, LevelPack, PaletteColor,
+    },
+    recipe::Recipe,
+    reverse::ReversePuzzle,
+    save::ResumeRun,
+    settings::{
+        play_effect, DifficultyRules, FailurePenalty, HintCost, Settings,
+    },
+    AppState,
+};
 
 pub struct GamePlugin;
 
@@ -8,15 +24,23 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<ResetLevelEvent>()
             .add_event::<PrepareLevelEvent>()
+            .add_event::<RestartRunEvent>()
             .add_event::<StartLevelEvent>()
             .add_event::<PlayerColorsChanged>()
+            .add_event::<UndoEvent>()
+            .add_event::<RedoEvent>()
+            .add_event::<SubmitEvent>()
+            .add_event::<HintEvent>()
+            .add_event::<HintGivenEvent>()
             .add_event::<LevelSucceededEvent>()
             .add_event::<LevelFailedEvent>()
             .add_event::<GameWonEvent>()
             .add_event::<GameLostEvent>()
+            .add_event::<TimeUpEvent>()
             .add_event::<AlertStartedEvent>()
             .add_event::<AlertEndedEvent>()
-            .insert_resource(GameOptions { mute: false })
+            .insert_resource(GameMode::Campaign)
+            .insert_resource(UndoRules::default())
             .add_system_set(
                 SystemSet::on_enter(AppState::InGame)
                     .with_system(setup)
@@ -33,7 +57,13 @@ impl Plugin for GamePlugin {
                     .after("ui_update")
                     .with_system(prepare_level)
                     .with_system(reset_level)
+                    .with_system(undo_selection)
+                    .with_system(redo_selection)
+                    .with_system(give_hint)
                     .with_system(check_level_finished)
+                    .with_system(tick_level_time)
+                    .with_system(check_time_up.after(tick_level_time))
+                    .with_system(show_time_up)
                     .with_system(show_game_lost)
                     .with_system(show_level_succeeded)
                     .with_system(show_level_failed)
@@ -48,56 +78,86 @@ impl Plugin for GamePlugin {
     }
 }
 
-const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
-const RED: Color = Color::rgb(1.0, 0.0, 0.0);
-const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
-const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
-const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
-
-pub const PALETTE_DATA: [Color; 5] = [WHITE, RED, YELLOW, BLUE, BLACK];
-
-pub const OBJECTIVES_DATA: &'static [&'static [Color]] = &[
-    // Complexity: 2
-    &[BLUE, YELLOW],
-    &[BLACK, YELLOW],
-    &[RED, YELLOW],
-    &[BLUE, WHITE],
-    &[RED, WHITE],
-    // Complexity: 3
-    &[RED, YELLOW, YELLOW],
-    &[YELLOW, BLUE, WHITE],
-    &[RED, BLUE, WHITE],
-    &[YELLOW, BLACK, BLACK],
-    &[RED, WHITE, WHITE],
-    // Complexity: 4
-    &[WHITE, RED, RED, YELLOW],
-    &[RED, YELLOW, BLUE, BLACK],
-    &[WHITE, YELLOW, YELLOW, BLUE],
-    &[WHITE, RED, YELLOW, YELLOW],
-    &[RED, RED, YELLOW, BLACK],
-    // Complexity: 5
-    &[YELLOW, BLACK, BLACK, BLACK, BLACK],
-    &[WHITE, RED, RED, RED, YELLOW],
-    &[WHITE, WHITE, WHITE, YELLOW, RED],
-    &[WHITE, RED, RED, RED, BLUE],
-    &[WHITE, WHITE, WHITE, RED, BLUE],
-    // Complexity: 6
-    &[RED, BLUE, YELLOW, YELLOW, BLACK, BLACK],
-    &[WHITE, WHITE, WHITE, WHITE, YELLOW, BLUE],
-    &[YELLOW, YELLOW, BLUE, BLACK, BLACK, BLACK],
-    &[BLACK, BLACK, RED, RED, RED, YELLOW],
-    &[RED, RED, BLUE, BLACK, BLACK, BLACK],
-];
-
-pub struct GameOptions {
-    pub mute: bool,
+/// Where the levels of a run come from, chosen from the main menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Plays through the levels of the active level pack.
+    Campaign,
+    /// Generates levels of rising complexity until the player runs out of
+    /// lives.
+    Endless,
+    /// Plays a short set of levels generated from the current date.
+    Daily,
+    /// Plays through the levels of the active level pack, each against a
+    /// countdown.
+    Timed,
+    /// Plays through the levels of the active level pack backwards: the
+    /// recipe is shown and the player picks the mixed color among swatches.
+    Reverse,
+}
+
+impl GameMode {
+    /// Number of levels in a run, or `None` if the run never ends.
+    pub fn level_count(&self, pack: &LevelPack) -> Option<u32> {
+        match self {
+            GameMode::Campaign => Some(pack.level_count()),
+            GameMode::Endless => None,
+            GameMode::Daily => Some(DAILY_LEVEL_COUNT),
+            GameMode::Timed => Some(pack.level_count()),
+            GameMode::Reverse => Some(pack.level_count()),
+        }
+    }
+}
+
+/// Seed that the random parts of a run are made from, for the modes that
+/// have them. Kept when the run is saved so that it resumes the same.
+pub struct RunSeed(pub u64);
+
+/// Seconds given per brush click of a level's complexity in timed mode.
+pub const SECONDS_PER_CLICK: f32 = 15.0;
+
+/// Restrictions on undoing brush clicks during a level.
+#[derive(Default)]
+pub struct UndoRules {
+    /// Points taken from the score for every undo.
+    pub cost: u32,
+    /// Number of undos allowed per level, or `None` for no limit.
+    pub limit: Option<u32>,
+}
+
+/// What a hint told the player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hint {
+    /// The objective uses the color at this index of the palette.
+    Reveal(usize),
+    /// The current mix can still be completed into the objective.
+    OnTrack,
+    /// The current mix has colors, or too much of them, that the objective
+    /// doesn't.
+    OffTrack,
+    /// The hint couldn't be paid for.
+    Unavailable,
 }
 
 pub struct GameState {
     pub lives_remaining: u32,
+    pub score: u32,
 }
 
 impl GameState {
+    /// State of a run starting with the given rules.
+    pub fn new(rules: &DifficultyRules) -> Self {
+        Self {
+            lives_remaining: rules.starting_lives,
+            score: 0,
+        }
+    }
+
+    /// Starts the run over, with the lives it began with and no score.
+    pub fn restart(&mut self, rules: &DifficultyRules) {
+        *self = Self::new(rules);
+    }
+
     pub fn is_lost(&self) -> bool {
         self.lives_remaining < 1
     }
@@ -105,72 +165,391 @@ impl GameState {
 
 pub struct LevelState {
     pub level_index: u32,
-    pub selected_colors: Vec<Color>,
-    pub objective_colors: Vec<Color>,
+    pub palette: Vec<PaletteColor>,
+    pub selected: Recipe,
+    pub objective: Recipe,
+    pub matching: MatchRule,
+    pub mixing: MixingModelKind,
+    /// Fewest brush clicks needed to make the objective.
+    pub complexity: u32,
+    pub amounts: Vec<BrushAmount>,
+    /// Index of the brush amount that clicks currently add.
+    pub amount_index: usize,
+    /// Number of brush clicks since the level was last reset.
+    pub clicks: u32,
+    /// Number of wrong answers given on this level.
+    pub retries: u32,
+    /// Brush clicks that can be undone, as palette index and parts.
+    pub history: Vec<(usize, u32)>,
+    /// Brush clicks that were undone and can be redone.
+    pub undone: Vec<(usize, u32)>,
+    /// Number of undos used on this level.
+    pub undos: u32,
+    /// Whether the selection is final because the level's outcome is shown.
+    pub locked: bool,
+    /// Number of hints used on this level.
+    pub hints: u32,
+    /// Palette indices of objective colors revealed by hints.
+    pub revealed: Vec<usize>,
+    /// Swatches to pick from when the level is played backwards.
+    pub reverse: Option<ReversePuzzle>,
 }
 
 impl LevelState {
-    pub fn new(level_index: u32, colors: Vec<Color>) -> Self {
+    pub fn new(
+        level_index: u32,
+        definition: LevelDefinition,
+        mixing: MixingModelKind,
+    ) -> Self {
         Self {
             level_index,
-            selected_colors: default(),
-            objective_colors: colors,
+            mixing,
+            complexity: definition.complexity(),
+            palette: definition.palette,
+            selected: default(),
+            objective: definition.objective,
+            matching: definition.matching,
+            amounts: definition.amounts,
+            amount_index: 0,
+            clicks: 0,
+            retries: 0,
+            history: default(),
+            undone: default(),
+            undos: 0,
+            locked: false,
+            hints: 0,
+            revealed: default(),
+            reverse: None,
         }
     }
 
     pub fn reset(&mut self) {
-        self.selected_colors.clear();
+        self.selected.clear();
+        if let Some(puzzle) = self.reverse.as_mut() {
+            puzzle.picked = None;
+        }
+        self.clicks = 0;
+        self.history.clear();
+        self.undone.clear();
+        self.locked = false;
+    }
+
+    /// Finds the most useful hint for the current mix, and records it.
+    ///
+    /// A mix that went wrong is pointed out first, then the objective's
+    /// colors are revealed one by one.
+    pub fn hint(&mut self) -> Hint {
+        self.hints += 1;
+        if !self.selected.is_part_of(&self.objective) {
+            return Hint::OffTrack;
+        }
+
+        let hidden = self
+            .objective
+            .iter()
+            .map(|(index, _)| index)
+            .find(|index| !self.revealed.contains(index));
+        match hidden {
+            Some(index) => {
+                self.revealed.push(index);
+                Hint::Reveal(index)
+            }
+            None => Hint::OnTrack,
+        }
+    }
+
+    /// The amount of paint that the next brush click adds.
+    pub fn amount(&self) -> Option<&BrushAmount> {
+        self.amounts.get(self.amount_index)
+    }
+
+    /// Picks the swatch at `index` of a reverse puzzle as the answer.
+    pub fn pick(&mut self, index: usize) {
+        if self.locked {
+            return;
+        }
+
+        if let Some(puzzle) = self.reverse.as_mut() {
+            if index < puzzle.candidates.len() {
+                puzzle.picked = Some(index);
+                self.clicks += 1;
+            }
+        }
+    }
+
+    /// Adds the current amount of the color at `index` to the mix.
+    pub fn select(&mut self, index: usize) {
+        if self.locked {
+            return;
+        }
+
+        let parts = self.amount().map_or(1, |amount| amount.parts);
+        self.selected.add_parts(index, parts);
+        self.clicks += 1;
+        self.history.push((index, parts));
+        self.undone.clear();
+    }
+
+    pub fn can_undo(&self, rules: &UndoRules) -> bool {
+        !self.locked
+            && !self.history.is_empty()
+            && rules.limit.is_none_or(|limit| self.undos < limit)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.locked && !self.undone.is_empty()
+    }
+
+    /// Replays brush clicks saved from a previous session.
+    pub fn restore_selection(&mut self, clicks: &[(usize, u32)]) {
+        for (index, parts) in clicks {
+            self.selected.add_parts(*index, *parts);
+            self.clicks += 1;
+            self.history.push((*index, *parts));
+        }
+    }
+
+    /// Takes back the last brush click. Returns whether a click was undone.
+    pub fn undo(&mut self, rules: &UndoRules) -> bool {
+        if !self.can_undo(rules) {
+            return false;
+        }
+
+        if let Some((index, parts)) = self.history.pop() {
+            self.selected.remove_parts(index, parts);
+            self.clicks -= 1;
+            self.undos += 1;
+            self.undone.push((index, parts));
+        }
+        true
+    }
+
+    /// Repeats the last undone brush click. Returns whether a click was
+    /// redone.
+    pub fn redo(&mut self) -> bool {
+        if !self.can_redo() {
+            return false;
+        }
+
+        if let Some((index, parts)) = self.undone.pop() {
+            self.selected.add_parts(index, parts);
+            self.clicks += 1;
+            self.history.push((index, parts));
+        }
+        true
+    }
+
+    pub fn cycle_amount(&mut self) {
+        if !self.amounts.is_empty() {
+            self.amount_index = (self.amount_index + 1) % self.amounts.len();
+        }
+    }
+
+    /// Whether every selected color comes from this level's palette.
+    pub fn is_selection_valid(&self) -> bool {
+        self.selected.fits_palette(self.palette.len())
+    }
+
+    /// Whether the player mixed or picked anything yet.
+    pub fn has_selection(&self) -> bool {
+        match &self.reverse {
+            Some(puzzle) => puzzle.picked.is_some(),
+            None => !self.selected.is_empty(),
+        }
+    }
+
+    /// The current mix, or the picked swatch of a reverse puzzle.
+    pub fn selected_color(&self) -> Color {
+        match self.reverse.as_ref().map(|puzzle| puzzle.picked_color()) {
+            Some(picked) => picked.unwrap_or(Color::NONE),
+            None => self.selected.mix(&self.palette, self.mixing.model()),
+        }
+    }
+
+    pub fn objective_color(&self) -> Color {
+        self.objective.mix(&self.palette, self.mixing.model())
     }
 
     pub fn is_color_found(&self) -> bool {
-        let selected_color = mix_colors(&self.selected_colors);
-        let objective_color = mix_colors(&self.objective_colors);
-        let mut total_diff = 0.0;
-        total_diff += f32::abs(objective_color.r() - selected_color.r());
-        total_diff += f32::abs(objective_color.g() - selected_color.g());
-        total_diff += f32::abs(objective_color.b() - selected_color.b());
+        if let Some(puzzle) = &self.reverse {
+            return puzzle.is_solved();
+        }
+        if !self.is_selection_valid() {
+            return false;
+        }
 
-        // NOTE: account for small differences in float multiplications
-        total_diff < 0.0001
+        self.selected.equivalent(&self.objective)
+            || self
+                .matching
+                .is_match(self.selected_color(), self.objective_color())
     }
 
-    pub fn is_last_level(&self) -> bool {
-        self.level_index == (OBJECTIVES_DATA.len() - 1) as u32
+    /// How close the current mix is to the objective, from 0.0 to 1.0.
+    pub fn closeness(&self) -> f32 {
+        if !self.has_selection() || !self.is_selection_valid() {
+            return 0.0;
+        }
+
+        self.matching
+            .closeness(self.selected_color(), self.objective_color())
     }
 
-    pub fn prepare_objective(level_index: u32) -> Option<Vec<Color>> {
-        if level_index >= OBJECTIVES_DATA.len() as u32 {
-            None
-        } else {
-            Some(OBJECTIVES_DATA[level_index as usize].into())
+    /// Points earned by solving this level with the current mix.
+    ///
+    /// Harder levels are worth more, a closer mix and a quick answer add to
+    /// that, and every retry divides the total. Each remaining life then adds
+    /// a small bonus.
+    pub fn points(&self, lives_remaining: u32, elapsed: f32) -> u32 {
+        let base = 100.0 * self.complexity as f32;
+        // NOTE: answering within 10 seconds per click earns the whole time
+        // bonus, which then fades out over the same duration.
+        let par_time = 10.0 * self.complexity.max(1) as f32;
+        let time_bonus =
+            1.0 - ((elapsed - par_time) / par_time).clamp(0.0, 1.0);
+        let closeness = match self.selected.equivalent(&self.objective) {
+            true => 1.0,
+            false => self.closeness(),
+        };
+
+        let points =
+            base * (closeness + 0.5 * time_bonus) / (1 + self.retries) as f32;
+        points.round() as u32 + 10 * lives_remaining
+    }
+
+    /// Whether the answer can be judged wrong, rather than still being made.
+    pub fn is_answer_final(&self, submit_mix: bool) -> bool {
+        match (&self.reverse, submit_mix) {
+            (Some(_), _) | (None, true) => self.has_selection(),
+            (None, false) => self.selected.len() >= self.objective.len(),
         }
     }
+
+    pub fn is_last_level(&self, level_count: Option<u32>) -> bool {
+        level_count.is_some_and(|count| self.level_index + 1 >= count)
+    }
+
+    pub fn prepare_objective(
+        pack: &LevelPack,
+        level_index: u32,
+    ) -> Option<LevelDefinition> {
+        pack.level(level_index).cloned()
+    }
+}
+
+/// Time spent on the current level.
+///
+/// Kept apart from [`LevelState`] so that the clock ticking every frame
+/// doesn't mark the level as changed.
+#[derive(Default)]
+pub struct LevelClock {
+    /// Seconds spent on this level, retries included.
+    pub elapsed: f32,
+    /// Seconds given to answer in timed mode.
+    pub time_limit: Option<f32>,
+    /// Seconds left before the countdown runs out, if there is one.
+    pub time_left: Option<f32>,
+}
+
+impl LevelClock {
+    /// Gives the level a countdown of `seconds`, which restarts whenever a
+    /// life is lost.
+    pub fn set_time_limit(&mut self, seconds: f32) {
+        self.time_limit = Some(seconds);
+        self.time_left = Some(seconds);
+    }
+
+    pub fn restart_countdown(&mut self) {
+        self.time_left = self.time_limit;
+    }
+
+    pub fn is_time_up(&self) -> bool {
+        self.time_left.is_some_and(|time_left| time_left <= 0.0)
+    }
 }
 
 struct AlertTimer(Timer, AlertType);
 
 #[derive(Component)]
 pub struct ColorSelector {
-    pub color: Color,
+    /// Index of the color in the level's palette.
+    pub index: usize,
 }
 
-fn setup(mut commands: Commands) {
-    commands.insert_resource(GameState { lives_remaining: 1 });
+// NOTE: a run is set up from the saved one, the settings and the level pack
+#[allow(clippy::too_many_arguments)]
+fn setup(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mixing: Res<MixingModelKind>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    resume: Option<Res<ResumeRun>>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+) {
+    // NOTE: a run keeps the rules it was started with, even if the settings
+    // change while it is paused or saved.
+    let rules = resume
+        .as_ref()
+        .and_then(|resume| resume.0.difficulty)
+        .unwrap_or_else(|| settings.difficulty_rules());
+    commands.insert_resource(rules);
+
+    commands.insert_resource(match resume {
+        Some(ref resume) => GameState {
+            lives_remaining: resume.0.lives_remaining,
+            score: resume.0.score,
+        },
+        None => GameState::new(&rules),
+    });
+
+    // NOTE: the time spent in the menu is as good a seed as any, and unlike
+    // system time it is available on every platform.
+    let seed = resume
+        .and_then(|resume| resume.0.seed)
+        .unwrap_or_else(|| time.time_since_startup().as_nanos() as u64);
+    match *mode {
+        GameMode::Endless => {
+            if let Some(pack) = packs.get(&active_pack.0) {
+                commands.insert_resource(LevelGenerator::new(
+                    seed,
+                    pack.palette.clone(),
+                    *mixing,
+                ));
+            }
+            commands.insert_resource(RunSeed(seed));
+        }
+        GameMode::Reverse => commands.insert_resource(RunSeed(seed)),
+        _ => (),
+    }
 }
 
 fn teardown(mut commands: Commands) {
     commands.remove_resource::<LevelState>();
+    commands.remove_resource::<LevelClock>();
     commands.remove_resource::<AlertTimer>();
+    commands.remove_resource::<DifficultyRules>();
+    commands.remove_resource::<LevelGenerator>();
+    commands.remove_resource::<RunSeed>();
+    commands.remove_resource::<ResumeRun>();
 }
 
 pub struct PrepareLevelEvent;
+/// Sends the run back to its first level, with a score of zero.
+pub struct RestartRunEvent;
 pub struct ResetLevelEvent;
-pub struct StartLevelEvent(pub u32);
+pub struct StartLevelEvent;
 pub struct PlayerColorsChanged;
+pub struct UndoEvent;
+pub struct RedoEvent;
+pub struct SubmitEvent;
+pub struct HintEvent;
+pub struct HintGivenEvent(pub Hint);
 pub struct LevelSucceededEvent;
 pub struct LevelFailedEvent;
 pub struct GameWonEvent;
 pub struct GameLostEvent;
+pub struct TimeUpEvent;
 
 #[derive(Clone, Copy)]
 pub enum AlertType {
@@ -178,6 +557,7 @@ pub enum AlertType {
     LevelSucceeded,
     GameLost,
     GameWon,
+    TimeUp,
 }
 
 pub struct AlertStartedEvent(pub AlertType, pub String);
@@ -187,24 +567,125 @@ fn prepare_first_level(mut events: EventWriter<PrepareLevelEvent>) {
     events.send(PrepareLevelEvent);
 }
 
+// NOTE: every mode picks its next level from different resources
+#[allow(clippy::too_many_arguments)]
 fn prepare_level(
     mut commands: Commands,
+    mut game: ResMut<GameState>,
+    rules: Res<DifficultyRules>,
     level: Option<Res<LevelState>>,
+    mode: Res<GameMode>,
+    mixing: Res<MixingModelKind>,
+    resume: Option<Res<ResumeRun>>,
+    mut generator: Option<ResMut<LevelGenerator>>,
+    run_seed: Option<Res<RunSeed>>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
     mut prepare_evr: EventReader<PrepareLevelEvent>,
+    mut restart_evr: EventReader<RestartRunEvent>,
     mut start_evw: EventWriter<StartLevelEvent>,
+    mut alert_evw: EventWriter<AlertStartedEvent>,
 ) {
-    for _ in prepare_evr.iter() {
-        let level_index =
-            level.as_ref().map_or(0, |level| level.level_index + 1);
-        let objective_colors = LevelState::prepare_objective(level_index);
-        if let Some(objective_colors) = objective_colors {
-            let new_level = LevelState::new(level_index, objective_colors);
+    let pack = match packs.get(&active_pack.0) {
+        Some(pack) => pack,
+        None => return,
+    };
+
+    let restarted = restart_evr.iter().count() > 0;
+    if restarted {
+        game.restart(&rules);
+        if let Some(generator) = generator.as_mut() {
+            generator.restart();
+        }
+    }
+
+    let prepared = prepare_evr.iter().count() > 0;
+    if prepared || restarted {
+        // NOTE: a resumed run starts at its saved level
+        let resumed = match level {
+            Some(_) => None,
+            None => resume.as_ref().map(|resume| &resume.0),
+        };
+        let level_index = match (&level, resumed) {
+            _ if restarted => 0,
+            (Some(level), _) => level.level_index + 1,
+            (None, Some(run)) => run.level_index,
+            (None, None) => 0,
+        };
+
+        if resumed.is_some() {
+            // generated levels depend on the ones generated before them
+            for skipped_index in 0..level_index {
+                level_definition(*mode, pack, &mut generator, skipped_index);
+            }
+        }
+
+        let definition =
+            level_definition(*mode, pack, &mut generator, level_index);
+        if let Some(definition) = definition {
+            let mut new_level =
+                LevelState::new(level_index, definition, *mixing);
+            let mut clock = LevelClock::default();
+            if *mode == GameMode::Timed {
+                let complexity = new_level.complexity.max(1) as f32;
+                clock.set_time_limit(SECONDS_PER_CLICK * complexity);
+            }
+            if *mode == GameMode::Reverse {
+                // NOTE: every level of a run gets its own swatches, which
+                // differ from one run to the next
+                let run_seed = run_seed.as_ref().map_or(0, |seed| seed.0);
+                new_level.reverse = Some(ReversePuzzle::generate(
+                    new_level.objective_color(),
+                    new_level.complexity,
+                    run_seed ^ u64::from(level_index).rotate_right(16),
+                ));
+            }
+            if let Some(run) = resumed {
+                new_level.retries = run.retries;
+                new_level.amount_index = run.amount_index;
+                new_level.hints = run.hints;
+                new_level.revealed = run.revealed.clone();
+                new_level.restore_selection(&run.selection);
+                clock.elapsed = run.elapsed;
+                if let Some(time_left) = run.time_left {
+                    clock.time_left = Some(time_left);
+                }
+                commands.remove_resource::<ResumeRun>();
+            }
             commands.insert_resource(new_level);
-            start_evw.send(StartLevelEvent(level_index));
+            commands.insert_resource(clock);
+            start_evw.send(StartLevelEvent);
+        } else {
+            // NOTE: only generated levels can be missing, when the pack's
+            // palette is too small to make any recipe
+            error!("level {} could not be prepared", level_index + 1);
+            alert_evw.send(AlertStartedEvent(
+                AlertType::GameLost,
+                "No level could be made from this palette".into(),
+            ));
         }
     }
 }
 
+fn level_definition(
+    mode: GameMode,
+    pack: &LevelPack,
+    generator: &mut Option<ResMut<LevelGenerator>>,
+    level_index: u32,
+) -> Option<LevelDefinition> {
+    match mode {
+        GameMode::Campaign | GameMode::Timed | GameMode::Reverse => {
+            LevelState::prepare_objective(pack, level_index)
+        }
+        GameMode::Endless => generator
+            .as_mut()
+            .and_then(|generator| generator.generate_level(level_index)),
+        GameMode::Daily => generator.as_mut().and_then(|generator| {
+            generator.generate_up_to(daily::level_complexity(level_index))
+        }),
+    }
+}
+
 fn reset_level(
     mut level: Option<ResMut<LevelState>>,
     mut evr: EventReader<ResetLevelEvent>,
@@ -218,28 +699,167 @@ fn reset_level(
     }
 }
 
+fn undo_selection(
+    mut game: ResMut<GameState>,
+    mut level: Option<ResMut<LevelState>>,
+    rules: Res<UndoRules>,
+    mut evr: EventReader<UndoEvent>,
+    mut changed_evw: EventWriter<PlayerColorsChanged>,
+) {
+    if let Some(level) = level.as_mut() {
+        for _ in evr.iter() {
+            if level.undo(&rules) {
+                game.score = game.score.saturating_sub(rules.cost);
+                changed_evw.send(PlayerColorsChanged);
+            }
+        }
+    }
+}
+
+fn redo_selection(
+    mut level: Option<ResMut<LevelState>>,
+    mut evr: EventReader<RedoEvent>,
+    mut changed_evw: EventWriter<PlayerColorsChanged>,
+) {
+    if let Some(level) = level.as_mut() {
+        for _ in evr.iter() {
+            if level.redo() {
+                changed_evw.send(PlayerColorsChanged);
+            }
+        }
+    }
+}
+
+fn give_hint(
+    mut game: ResMut<GameState>,
+    mut level: Option<ResMut<LevelState>>,
+    rules: Res<DifficultyRules>,
+    mut evr: EventReader<HintEvent>,
+    mut given_evw: EventWriter<HintGivenEvent>,
+) {
+    if let Some(level) = level.as_mut() {
+        for _ in evr.iter() {
+            // NOTE: reverse puzzles already show the whole recipe
+            if level.locked || level.reverse.is_some() {
+                continue;
+            }
+
+            let affordable = match rules.hint_cost {
+                HintCost::Points(_) => true,
+                HintCost::Life => game.lives_remaining > 1,
+            };
+            if !affordable {
+                given_evw.send(HintGivenEvent(Hint::Unavailable));
+                continue;
+            }
+
+            match rules.hint_cost {
+                HintCost::Points(points) => {
+                    game.score = game.score.saturating_sub(points)
+                }
+                HintCost::Life => game.lives_remaining -= 1,
+            }
+            given_evw.send(HintGivenEvent(level.hint()));
+        }
+    }
+}
+
+fn tick_level_time(
+    time: Res<Time>,
+    level: Option<Res<LevelState>>,
+    mut clock: Option<ResMut<LevelClock>>,
+) {
+    if let (Some(level), Some(clock)) = (level, clock.as_mut()) {
+        if !level.locked {
+            clock.elapsed += time.delta_seconds();
+            if let Some(time_left) = clock.time_left.as_mut() {
+                *time_left = (*time_left - time.delta_seconds()).max(0.0);
+            }
+        }
+    }
+}
+
+fn check_time_up(
+    mut game: ResMut<GameState>,
+    mut level: Option<ResMut<LevelState>>,
+    mut clock: Option<ResMut<LevelClock>>,
+    mut time_up_evw: EventWriter<TimeUpEvent>,
+    mut lost_evw: EventWriter<GameLostEvent>,
+) {
+    if let (Some(level), Some(clock)) = (level.as_mut(), clock.as_mut()) {
+        if level.locked || !clock.is_time_up() {
+            return;
+        }
+
+        level.locked = true;
+        level.retries += 1;
+        clock.restart_countdown();
+        game.lives_remaining -= 1;
+        if game.is_lost() {
+            lost_evw.send(GameLostEvent);
+        } else {
+            time_up_evw.send(TimeUpEvent);
+        }
+    }
+}
+
+// NOTE: every event that judging a mix can lead to is its own argument
+#[allow(clippy::too_many_arguments)]
 fn check_level_finished(
     mut game: ResMut<GameState>,
-    level: Option<ResMut<LevelState>>,
-    mut evr: EventReader<PlayerColorsChanged>,
+    mut level: Option<ResMut<LevelState>>,
+    mut clock: Option<ResMut<LevelClock>>,
+    mode: Res<GameMode>,
+    settings: Res<Settings>,
+    rules: Res<DifficultyRules>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+    mut changed_evr: EventReader<PlayerColorsChanged>,
+    mut submit_evr: EventReader<SubmitEvent>,
     mut succeeded_evw: EventWriter<LevelSucceededEvent>,
     mut failed_evw: EventWriter<LevelFailedEvent>,
     mut won_evw: EventWriter<GameWonEvent>,
     mut lost_evw: EventWriter<GameLostEvent>,
 ) {
-    if evr.iter().count() < 1 {
+    let changed = changed_evr.iter().count() > 0;
+    let submitted = submit_evr.iter().count() > 0;
+    let judged = match settings.submit_mix {
+        true => submitted,
+        false => changed,
+    };
+    if !judged {
         return;
     }
 
-    if let Some(ref level) = level {
+    let pack = match packs.get(&active_pack.0) {
+        Some(pack) => pack,
+        None => return,
+    };
+
+    if let Some(level) = level.as_mut() {
+        if level.locked {
+            return;
+        }
+
         if level.is_color_found() {
-            if level.is_last_level() {
+            level.locked = true;
+            let elapsed = clock.as_ref().map_or(0.0, |clock| clock.elapsed);
+            game.score += level.points(game.lives_remaining, elapsed);
+            if level.is_last_level(mode.level_count(pack)) {
                 won_evw.send(GameWonEvent);
             } else {
-                game.lives_remaining = u32::min(3, game.lives_remaining + 1);
+                game.lives_remaining = u32::min(
+                    rules.max_lives,
+                    game.lives_remaining + rules.lives_per_success,
+                );
                 succeeded_evw.send(LevelSucceededEvent);
             }
-        } else if level.selected_colors.len() >= level.objective_colors.len() {
+        } else if level.is_answer_final(settings.submit_mix) {
+            level.locked = true;
+            level.retries += 1;
+            if let Some(clock) = clock.as_mut() {
+                clock.restart_countdown();
+            }
             game.lives_remaining -= 1;
             if game.is_lost() {
                 lost_evw.send(GameLostEvent);
@@ -263,36 +883,64 @@ fn show_level_succeeded(
 }
 
 fn show_level_failed(
+    level: Option<Res<LevelState>>,
     mut failed_evr: EventReader<LevelFailedEvent>,
     mut alert_evw: EventWriter<AlertStartedEvent>,
 ) {
     if failed_evr.iter().count() > 0 {
+        let closeness = level.map_or(0.0, |level| level.closeness());
         alert_evw.send(AlertStartedEvent(
             AlertType::LevelFailed,
-            "Wrong answer!".into(),
+            format!("Wrong answer! ({:.0}% close)", closeness * 100.0),
         ))
     }
 }
 
+fn show_time_up(
+    mut time_up_evr: EventReader<TimeUpEvent>,
+    mut alert_evw: EventWriter<AlertStartedEvent>,
+) {
+    if time_up_evr.iter().count() > 0 {
+        alert_evw
+            .send(AlertStartedEvent(AlertType::TimeUp, "Time's up!".into()))
+    }
+}
+
 fn show_game_won(
+    mode: Res<GameMode>,
     mut won_evr: EventReader<GameWonEvent>,
     mut alert_evw: EventWriter<AlertStartedEvent>,
 ) {
     if won_evr.iter().count() > 0 {
-        alert_evw.send(AlertStartedEvent(
-            AlertType::GameWon,
-            "Congratulations, you won!".into(),
-        ))
+        let text = match *mode {
+            GameMode::Daily => "Daily challenge complete!",
+            _ => "Congratulations, you won!",
+        };
+        alert_evw.send(AlertStartedEvent(AlertType::GameWon, text.into()))
     }
 }
 
 fn show_game_lost(
+    mode: Res<GameMode>,
+    game: Res<GameState>,
+    level: Option<Res<LevelState>>,
     mut lost_evr: EventReader<GameLostEvent>,
     mut alert_evw: EventWriter<AlertStartedEvent>,
 ) {
     if lost_evr.iter().count() > 0 {
-        alert_evw
-            .send(AlertStartedEvent(AlertType::GameLost, "You lost!".into()));
+        let text = match (*mode, level) {
+            (GameMode::Endless, Some(level)) => format!(
+                "Game over! Reached level {} with a score of {}",
+                level.level_index + 1,
+                game.score
+            ),
+            (GameMode::Daily, Some(level)) => format!(
+                "Daily challenge over: {}/{} solved",
+                level.level_index, DAILY_LEVEL_COUNT
+            ),
+            _ => "You lost!".into(),
+        };
+        alert_evw.send(AlertStartedEvent(AlertType::GameLost, text));
     }
 }
 
@@ -306,6 +954,7 @@ fn setup_alert_timer(
             AlertType::LevelSucceeded => Some(1.0),
             AlertType::GameLost => None,
             AlertType::GameWon => None,
+            AlertType::TimeUp => Some(2.0),
         };
 
         if let Some(duration) = duration {
@@ -323,25 +972,29 @@ fn update_alert_timer(
     alert_timer: Option<ResMut<AlertTimer>>,
     mut evw: EventWriter<AlertEndedEvent>,
 ) {
-    alert_timer.map(|mut alert_timer| {
+    if let Some(mut alert_timer) = alert_timer {
         alert_timer.0.tick(time.delta());
 
         if alert_timer.0.finished() {
             evw.send(AlertEndedEvent(alert_timer.1));
             commands.remove_resource::<AlertTimer>();
         }
-    });
+    }
 }
 
 fn update_level_after_alert(
-    game: Res<GameState>,
+    rules: Res<DifficultyRules>,
     mut evr: EventReader<AlertEndedEvent>,
     mut reset_evw: EventWriter<ResetLevelEvent>,
+    mut restart_evw: EventWriter<RestartRunEvent>,
     mut prepare_evw: EventWriter<PrepareLevelEvent>,
 ) {
     if let Some(event) = evr.iter().last() {
         match event.0 {
-            AlertType::LevelFailed => reset_evw.send(ResetLevelEvent),
+            AlertType::LevelFailed | AlertType::TimeUp => match rules.failure {
+                FailurePenalty::RetryLevel => reset_evw.send(ResetLevelEvent),
+                FailurePenalty::RestartRun => restart_evw.send(RestartRunEvent),
+            },
             AlertType::LevelSucceeded => prepare_evw.send(PrepareLevelEvent),
             _ => (),
         }
@@ -352,20 +1005,151 @@ fn play_alert_sound(
     mut alert_evr: EventReader<AlertStartedEvent>,
     asset_server: Res<AssetServer>,
     audio: Res<Audio>,
-    options: Res<GameOptions>,
+    settings: Res<Settings>,
 ) {
-    if options.mute {
-        return;
-    }
-
     if let Some(event) = alert_evr.iter().last() {
         let source = match event.0 {
             AlertType::LevelFailed => "audio/failure.ogg",
             AlertType::LevelSucceeded => "audio/succeeded.ogg",
             AlertType::GameLost => "audio/lost.ogg",
             AlertType::GameWon => "audio/won.ogg",
+            AlertType::TimeUp => "audio/time_up.wav",
         };
 
-        audio.play(asset_server.load(source));
+        play_effect(&audio, &asset_server, &settings, source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Difficulty;
+
+    fn level(objective: &[usize]) -> LevelState {
+        let palette = [Color::RED, Color::YELLOW, Color::BLUE]
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| PaletteColor {
+                name: format!("Color {}", index),
+                color,
+            })
+            .collect();
+        let definition = LevelDefinition {
+            palette,
+            objective: Recipe::from_indices(objective.iter().copied()),
+            matching: default(),
+            amounts: vec![BrushAmount::drop()],
+        };
+        LevelState::new(0, definition, MixingModelKind::default())
+    }
+
+    #[test]
+    fn points_reward_quick_answers_without_retries() {
+        let mut level = level(&[0, 1]);
+        level.select(0);
+        level.select(1);
+
+        // NOTE: the par time of a level of 2 clicks is 20 seconds
+        assert_eq!(level.points(0, 5.0), 300);
+        assert_eq!(level.points(0, 30.0), 250);
+        assert_eq!(level.points(0, 60.0), 200);
+        assert_eq!(level.points(2, 5.0), 320);
+
+        level.retries = 1;
+        assert_eq!(level.points(0, 5.0), 150);
+    }
+
+    #[test]
+    fn new_clicks_clear_what_can_be_redone() {
+        let rules = UndoRules::default();
+        let mut level = level(&[0, 1]);
+        level.select(0);
+        level.select(2);
+
+        assert!(level.undo(&rules));
+        assert!(level.can_redo());
+        assert!(level.redo());
+        assert_eq!(level.selected, Recipe::from_indices([0, 2]));
+
+        assert!(level.undo(&rules));
+        level.select(1);
+        assert!(!level.can_redo());
+        assert!(!level.redo());
+        assert_eq!(level.selected, Recipe::from_indices([0, 1]));
+        assert_eq!(level.clicks, 2);
+    }
+
+    #[test]
+    fn undos_stop_at_the_limit() {
+        let rules = UndoRules {
+            cost: 0,
+            limit: Some(2),
+        };
+        let mut level = level(&[0, 1]);
+        for index in [0, 1, 2] {
+            level.select(index);
+        }
+
+        assert!(level.undo(&rules));
+        // NOTE: redoing doesn't give the undo back
+        assert!(level.redo());
+        assert!(level.undo(&rules));
+        assert!(!level.can_undo(&rules));
+        assert!(!level.undo(&rules));
+        assert_eq!(level.undos, 2);
+        assert_eq!(level.selected, Recipe::from_indices([0, 1]));
+
+        level.locked = true;
+        assert!(!level.undo(&UndoRules::default()));
+    }
+
+    fn events_after_alert(
+        rules: DifficultyRules,
+        alert: AlertType,
+    ) -> (usize, usize) {
+        let mut world = World::new();
+        world.insert_resource(rules);
+        world.insert_resource(Events::<ResetLevelEvent>::default());
+        world.insert_resource(Events::<RestartRunEvent>::default());
+        world.insert_resource(Events::<PrepareLevelEvent>::default());
+        let mut alerts = Events::<AlertEndedEvent>::default();
+        alerts.send(AlertEndedEvent(alert));
+        world.insert_resource(alerts);
+
+        SystemStage::single(update_level_after_alert).run(&mut world);
+
+        let resets = world.resource::<Events<ResetLevelEvent>>().len();
+        let restarts = world.resource::<Events<RestartRunEvent>>().len();
+        (resets, restarts)
+    }
+
+    #[test]
+    fn failing_restarts_the_run_when_the_rules_say_so() {
+        let rules = Difficulty::Hard.preset();
+        assert_eq!(rules.failure, FailurePenalty::RestartRun);
+        assert_eq!(events_after_alert(rules, AlertType::LevelFailed), (0, 1));
+        assert_eq!(events_after_alert(rules, AlertType::TimeUp), (0, 1));
+
+        let rules = Difficulty::Normal.preset();
+        assert_eq!(events_after_alert(rules, AlertType::LevelFailed), (1, 0));
+    }
+
+    #[test]
+    fn restarting_the_run_gives_back_the_starting_lives() {
+        let rules = DifficultyRules {
+            starting_lives: 2,
+            max_lives: 4,
+            ..Difficulty::Hard.preset()
+        };
+        let mut game = GameState {
+            lives_remaining: 1,
+            score: 340,
+        };
+
+        game.restart(&rules);
+
+        assert_eq!(game.lives_remaining, 2);
+        assert_eq!(game.score, 0);
+        assert!(!game.is_lost());
     }
 }
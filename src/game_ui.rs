@@ -1,14 +1,22 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::EntityCommands, prelude::*, window::WindowFocused};
 
+use crate::color_distance::srgb_to_lab;
+use crate::color_vision::simulate_color;
+use crate::input::{Action, ActionState};
+use crate::level_pack::{ActiveLevelPack, LevelPack, PaletteColor};
+use crate::reverse::ReversePuzzle;
+use crate::settings::{play_effect, DifficultyRules, Settings};
 use crate::widgets::{
-    spawn_game_button, spawn_game_indicator, GameButton, GameButtonLabel,
-    GameIndicator, GameIndicatorLabel,
+    spawn_game_button, spawn_game_indicator, Focusable, GameButton,
+    GameButtonLabel, GameIndicator, GameIndicatorLabel,
 };
-use crate::{color_mixer::mix_colors, AppState};
+use crate::AppState;
 
 use crate::game::{
-    ColorSelector, GameState, LevelState, PlayerColorsChanged,
-    StartLevelEvent, OBJECTIVES_DATA, PALETTE_DATA, AlertStartedEvent, AlertEndedEvent, GameOptions,
+    AlertEndedEvent, AlertStartedEvent, ColorSelector, GameMode, GameState,
+    Hint, HintEvent, HintGivenEvent, LevelClock, LevelState,
+    PlayerColorsChanged, RedoEvent, ResetLevelEvent, StartLevelEvent,
+    SubmitEvent, UndoEvent,
 };
 
 pub struct GameUiPlugin;
@@ -21,19 +29,51 @@ impl Plugin for GameUiPlugin {
         .add_system_set(
             SystemSet::on_exit(AppState::InGame).with_system(teardown),
         )
+        // NOTE: the pause overlay is pushed over the game, which hides
+        // meanwhile
+        .add_system_set(
+            SystemSet::on_pause(AppState::InGame).with_system(hide_ui),
+        )
+        .add_system_set(
+            SystemSet::on_resume(AppState::InGame).with_system(show_ui),
+        )
         .add_system_set(
             SystemSet::on_update(AppState::InGame)
                 .label("ui_update")
                 .before(GameButtonLabel)
                 .before(GameIndicatorLabel)
                 .with_system(handle_exit_clicked)
+                .with_system(handle_pause)
+                .with_system(handle_reset_keys)
+                .with_system(handle_amount_clicked)
+                .with_system(update_amount_button)
+                .with_system(handle_undo_clicked)
+                .with_system(handle_redo_clicked)
+                .with_system(handle_undo_keys)
+                .with_system(handle_submit_clicked)
+                .with_system(handle_submit_keys)
+                .with_system(apply_submit_setting)
+                .with_system(handle_hint_clicked)
+                .with_system(handle_hint_keys)
+                .with_system(update_hint_indicator)
+                .with_system(update_hint_markers)
                 .with_system(update_player_color)
                 .with_system(update_objective_color)
+                .with_system(update_color_readouts)
+                .with_system(update_brush_colors)
                 .with_system(update_complexity_indicator)
                 .with_system(update_selection_indicator)
+                .with_system(update_closeness_indicator)
                 .with_system(update_level_indicator)
                 .with_system(update_lives_indicator)
+                .with_system(update_score_indicator)
+                .with_system(update_time_indicator)
                 .with_system(handle_color_clicked)
+                .with_system(handle_color_keys)
+                .with_system(handle_candidate_clicked)
+                .with_system(update_candidates)
+                .with_system(highlight_focused_brush)
+                .with_system(rebuild_palette)
                 .with_system(show_alert)
                 .with_system(hide_alert),
         );
@@ -46,11 +86,48 @@ struct GameUIRoot;
 #[derive(Component)]
 struct MenuButton;
 
+#[derive(Component)]
+struct PauseButton;
+
+#[derive(Component)]
+struct AmountButton;
+
+#[derive(Component)]
+struct UndoButton;
+
+#[derive(Component)]
+struct RedoButton;
+
+#[derive(Component)]
+struct SubmitButton;
+
+/// Holds the submit button, hidden unless mixes are submitted.
+#[derive(Component)]
+struct SubmitSlot;
+
+#[derive(Component)]
+struct HintButton;
+
+#[derive(Component)]
+struct HintIndicator;
+
+/// Shown on a brush once a hint revealed that the objective uses it.
+#[derive(Component)]
+struct HintMarker;
+
 #[derive(Component)]
 struct ObjectiveColor;
 
 #[derive(Component)]
-struct PlayerColor;
+pub(crate) struct PlayerColor;
+
+/// Describes the color of the panel it is in, in numbers.
+#[derive(Component)]
+pub(crate) struct ColorReadout;
+
+/// Names the color of the brush it is on.
+#[derive(Component)]
+struct BrushLabel;
 
 #[derive(Component)]
 struct ComplexityIndicator;
@@ -58,19 +135,42 @@ struct ComplexityIndicator;
 #[derive(Component)]
 struct SelectionIndicator;
 
+#[derive(Component)]
+struct ClosenessIndicator;
+
 #[derive(Component)]
 struct LevelIndicator;
 
 #[derive(Component)]
 struct LivesIndicator;
 
+#[derive(Component)]
+struct ScoreIndicator;
+
+#[derive(Component)]
+struct TimeIndicator;
+
 #[derive(Component)]
 struct AlertVisibility(bool);
 
 #[derive(Component)]
 struct AlertTextNode;
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+#[derive(Component)]
+struct PaletteRow;
+
+/// A swatch of a reverse puzzle, by index among its candidates.
+#[derive(Component)]
+struct CandidateSelector {
+    index: usize,
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    mode: Res<GameMode>,
+) {
     let menu_button = spawn_game_button(
         &mut commands,
         &asset_server,
@@ -80,12 +180,21 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     );
     commands.entity(menu_button).insert(MenuButton);
 
+    let pause_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Pause".into(),
+        },
+    );
+    commands.entity(pause_button).insert(PauseButton);
+
     let level_indicator = spawn_game_indicator(
         &mut commands,
         &asset_server,
         GameIndicator {
             label: "Level".into(),
-            value: "1/25".into(),
+            value: "-".into(),
         },
     );
     commands.entity(level_indicator).insert(LevelIndicator);
@@ -95,16 +204,39 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         &asset_server,
         GameIndicator {
             label: "Lives".into(),
-            value: "1/3".into(),
+            value: "-".into(),
         },
     );
     commands.entity(lives_indicator).insert(LivesIndicator);
 
+    let score_indicator = spawn_game_indicator(
+        &mut commands,
+        &asset_server,
+        GameIndicator {
+            label: "Score".into(),
+            value: "0".into(),
+        },
+    );
+    commands.entity(score_indicator).insert(ScoreIndicator);
+
+    let time_indicator = (*mode == GameMode::Timed).then(|| {
+        let time_indicator = spawn_game_indicator(
+            &mut commands,
+            &asset_server,
+            GameIndicator {
+                label: "Time".into(),
+                value: "-".into(),
+            },
+        );
+        commands.entity(time_indicator).insert(TimeIndicator);
+        time_indicator
+    });
+
     let complexity_indicator = spawn_game_indicator(
         &mut commands,
         &asset_server,
         GameIndicator {
-            label: "Level complexity".into(),
+            label: complexity_label(&settings).into(),
             value: "2".into(),
         },
     );
@@ -112,17 +244,124 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         .entity(complexity_indicator)
         .insert(ComplexityIndicator);
 
-    let selection_indicator = spawn_game_indicator(
+    // NOTE: reverse puzzles are answered by picking a swatch, so there is
+    // nothing to mix, undo or give hints about
+    let mixing_controls = (*mode != GameMode::Reverse).then(|| {
+        let selection_indicator = spawn_game_indicator(
+            &mut commands,
+            &asset_server,
+            GameIndicator {
+                label: "Selected colors".into(),
+                value: "0".into(),
+            },
+        );
+        commands
+            .entity(selection_indicator)
+            .insert(SelectionIndicator);
+
+        let closeness_indicator = spawn_game_indicator(
+            &mut commands,
+            &asset_server,
+            GameIndicator {
+                label: "Closeness".into(),
+                value: "-".into(),
+            },
+        );
+        commands
+            .entity(closeness_indicator)
+            .insert(ClosenessIndicator);
+
+        let amount_button = spawn_game_button(
+            &mut commands,
+            &asset_server,
+            GameButton {
+                text: "Drop".into(),
+            },
+        );
+        commands.entity(amount_button).insert(AmountButton);
+
+        let undo_button = spawn_game_button(
+            &mut commands,
+            &asset_server,
+            GameButton {
+                text: "Undo".into(),
+            },
+        );
+        commands
+            .entity(undo_button)
+            .insert(UndoButton)
+            .insert(AlertVisibility(false));
+
+        let redo_button = spawn_game_button(
+            &mut commands,
+            &asset_server,
+            GameButton {
+                text: "Redo".into(),
+            },
+        );
+        commands
+            .entity(redo_button)
+            .insert(RedoButton)
+            .insert(AlertVisibility(false));
+
+        let hint_button = spawn_game_button(
+            &mut commands,
+            &asset_server,
+            GameButton {
+                text: "Hint".into(),
+            },
+        );
+        commands
+            .entity(hint_button)
+            .insert(HintButton)
+            .insert(AlertVisibility(false));
+
+        let hint_indicator = spawn_game_indicator(
+            &mut commands,
+            &asset_server,
+            GameIndicator {
+                label: "Hint".into(),
+                value: "-".into(),
+            },
+        );
+        commands.entity(hint_indicator).insert(HintIndicator);
+
+        [
+            selection_indicator,
+            closeness_indicator,
+            hint_indicator,
+            amount_button,
+            undo_button,
+            redo_button,
+            hint_button,
+        ]
+    });
+
+    // NOTE: submitting can be turned on or off from the pause menu, so the
+    // button is always there and only shown when the setting is on
+    let submit_button = spawn_game_button(
         &mut commands,
         &asset_server,
-        GameIndicator {
-            label: "Selected colors".into(),
-            value: "0".into(),
+        GameButton {
+            text: "Submit".into(),
         },
     );
     commands
-        .entity(selection_indicator)
-        .insert(SelectionIndicator);
+        .entity(submit_button)
+        .insert(SubmitButton)
+        .insert(AlertVisibility(false));
+    let submit_slot = commands
+        .spawn_bundle(NodeBundle {
+            color: Color::NONE.into(),
+            style: Style {
+                display: submit_display(&settings),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(SubmitSlot)
+        .add_child(submit_button)
+        .id();
 
     commands
         .spawn_bundle(NodeBundle {
@@ -137,22 +376,26 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         })
         .insert(GameUIRoot)
         .with_children(|main_container| {
-            main_container
-                .spawn_bundle(NodeBundle {
-                    color: Color::NONE.into(),
-                    style: Style {
-                        justify_content: JustifyContent::SpaceBetween,
-                        align_content: AlignContent::Center,
-                        margin: UiRect {
-                            bottom: Val::Px(5.0),
-                            ..default()
-                        },
+            let mut top_bar = main_container.spawn_bundle(NodeBundle {
+                color: Color::NONE.into(),
+                style: Style {
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_content: AlignContent::Center,
+                    margin: UiRect {
+                        bottom: Val::Px(5.0),
                         ..default()
                     },
                     ..default()
-                })
+                },
+                ..default()
+            });
+            top_bar
                 .add_child(level_indicator)
-                .add_child(lives_indicator);
+                .add_child(score_indicator);
+            if let Some(time_indicator) = time_indicator {
+                top_bar.add_child(time_indicator);
+            }
+            top_bar.add_child(lives_indicator);
 
             main_container
                 .spawn_bundle(NodeBundle {
@@ -181,7 +424,10 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                             color: Color::NONE.into(),
                             ..default()
                         })
-                        .insert(ObjectiveColor);
+                        .insert(ObjectiveColor)
+                        .with_children(|panel| {
+                            spawn_color_readout(panel, &asset_server);
+                        });
 
                     board_section
                         .spawn_bundle(NodeBundle {
@@ -195,21 +441,26 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                             color: Color::NONE.into(),
                             ..default()
                         })
-                        .insert(PlayerColor);
+                        .insert(PlayerColor)
+                        .with_children(|panel| {
+                            spawn_color_readout(panel, &asset_server);
+                        });
                 });
 
-            main_container
-                .spawn_bundle(NodeBundle {
-                    color: Color::NONE.into(),
-                    style: Style {
-                        justify_content: JustifyContent::SpaceAround,
-                        align_content: AlignContent::Center,
-                        ..default()
-                    },
+            let mut controls_row = main_container.spawn_bundle(NodeBundle {
+                color: Color::NONE.into(),
+                style: Style {
+                    justify_content: JustifyContent::SpaceAround,
+                    align_content: AlignContent::Center,
                     ..default()
-                })
-                .add_child(complexity_indicator)
-                .add_child(selection_indicator);
+                },
+                ..default()
+            });
+            controls_row.add_child(complexity_indicator);
+            for control in mixing_controls.into_iter().flatten() {
+                controls_row.add_child(control);
+            }
+            controls_row.add_child(submit_slot);
 
             main_container
                 .spawn_bundle(NodeBundle {
@@ -225,6 +476,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                     color: Color::NONE.into(),
                     ..default()
                 })
+                .insert(PaletteRow)
                 .with_children(|bottom_section| {
                     bottom_section
                         .spawn_bundle(TextBundle {
@@ -244,30 +496,222 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                         })
                         .insert(AlertTextNode)
                         .insert(AlertVisibility(true));
+                });
 
-                    PALETTE_DATA.iter().for_each(|color| {
-                        bottom_section
-                            .spawn_bundle(ButtonBundle {
-                                image: asset_server.load("brush.png").into(),
-                                style: Style {
-                                    display: Display::Flex,
-                                    size: Size::new(
-                                        Val::Px(200.0),
-                                        Val::Px(70.0),
-                                    ),
-                                    ..default()
-                                },
-                                color: (*color).into(),
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .add_child(pause_button)
+                .add_child(menu_button);
+        });
+}
+
+pub(crate) fn spawn_color_readout(
+    panel: &mut ChildBuilder,
+    asset_server: &AssetServer,
+) {
+    panel
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("edosz.ttf"),
+                    font_size: 22.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                display: Display::None,
+                margin: UiRect::all(Val::Px(10.0)),
+                ..default()
+            }),
+        )
+        .insert(ColorReadout);
+}
+
+/// Text color that stands out on `background`.
+pub(crate) fn contrasting_text(background: Color) -> Color {
+    let [r, g, b, _] = background.as_linear_rgba_f32();
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    match luminance > 0.18 {
+        true => Color::BLACK,
+        false => Color::WHITE,
+    }
+}
+
+/// Describes `color` in hex and CIELAB, for players who can't rely on hue.
+pub(crate) fn describe_color(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_f32();
+    let hex = [r, g, b].map(|channel| (channel * 255.0).round() as u8);
+    let [l, a, b] = srgb_to_lab([r, g, b].map(f64::from));
+    format!(
+        "#{:02X}{:02X}{:02X}\nL {:.0} a {:.0} b {:.0}",
+        hex[0], hex[1], hex[2], l, a, b
+    )
+}
+
+pub(crate) fn display(visible: bool) -> Display {
+    match visible {
+        true => Display::Flex,
+        false => Display::None,
+    }
+}
+
+// NOTE: a reverse level swaps the brushes for candidates, so both are cleared
+#[allow(clippy::type_complexity)]
+fn rebuild_palette(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    level: Option<Res<LevelState>>,
+    mut start_evr: EventReader<StartLevelEvent>,
+    row_query: Query<Entity, With<PaletteRow>>,
+    selector_query: Query<
+        Entity,
+        Or<(With<ColorSelector>, With<CandidateSelector>)>,
+    >,
+) {
+    if start_evr.iter().count() < 1 {
+        return;
+    }
+
+    let level = match level {
+        Some(level) => level,
+        None => return,
+    };
+
+    for entity in selector_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for row in row_query.iter() {
+        commands.entity(row).with_children(|bottom_section| {
+            if let Some(puzzle) = &level.reverse {
+                spawn_candidates(
+                    bottom_section,
+                    &asset_server,
+                    &settings,
+                    puzzle,
+                );
+                return;
+            }
+
+            level.palette.iter().enumerate().for_each(|(index, entry)| {
+                spawn_brush(
+                    bottom_section,
+                    &asset_server,
+                    &settings,
+                    index,
+                    entry,
+                )
+                .insert(AlertVisibility(false))
+                .with_children(|brush| {
+                    brush
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                display: display(
+                                    level.revealed.contains(&index),
+                                ),
+                                size: Size::new(Val::Px(12.0), Val::Px(12.0)),
+                                margin: UiRect::all(Val::Px(6.0)),
                                 ..default()
-                            })
-                            .insert(ColorSelector {
-                                color: (*color).into(),
-                            })
-                            .insert(AlertVisibility(false));
-                    });
+                            },
+                            color: Color::WHITE.into(),
+                            ..default()
+                        })
+                        .insert(HintMarker);
                 });
-        })
-        .add_child(menu_button);
+            });
+        });
+    }
+}
+
+/// Spawns the swatches of a reverse puzzle, which replace the brushes.
+fn spawn_candidates(
+    bottom_section: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    puzzle: &ReversePuzzle,
+) {
+    for (index, candidate) in puzzle.candidates.iter().enumerate() {
+        let color = simulate_color(*candidate, settings.color_vision);
+        bottom_section
+            .spawn_bundle(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(110.0), Val::Px(110.0)),
+                    margin: UiRect::all(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                color: color.into(),
+                ..default()
+            })
+            .insert(CandidateSelector { index })
+            .insert(Focusable::default())
+            .insert(AlertVisibility(false))
+            .with_children(|swatch| {
+                swatch.spawn_bundle(TextBundle::from_section(
+                    (index + 1).to_string(),
+                    TextStyle {
+                        font: asset_server.load("edosz.ttf"),
+                        font_size: 30.0,
+                        color: contrasting_text(color),
+                    },
+                ));
+            });
+    }
+}
+
+/// Spawns the button that adds the palette color at `index` to the mix.
+pub(crate) fn spawn_brush<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    index: usize,
+    entry: &PaletteColor,
+) -> EntityCommands<'w, 's, 'a> {
+    let color = simulate_color(entry.color, settings.color_vision);
+    let mut brush = parent.spawn_bundle(ButtonBundle {
+        image: asset_server.load("brush.png").into(),
+        style: Style {
+            display: Display::Flex,
+            size: Size::new(Val::Px(200.0), Val::Px(70.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: color.into(),
+        ..default()
+    });
+    brush
+        .insert(ColorSelector { index })
+        .insert(Focusable::default())
+        .with_children(|brush| {
+            brush
+                .spawn_bundle(
+                    TextBundle::from_section(
+                        format!("{} {}", index + 1, entry.name),
+                        TextStyle {
+                            font: asset_server.load("edosz.ttf"),
+                            font_size: 20.0,
+                            color: contrasting_text(color),
+                        },
+                    )
+                    .with_style(Style {
+                        display: display(settings.colorblind),
+                        ..default()
+                    }),
+                )
+                .insert(BrushLabel);
+        });
+    brush
 }
 
 fn teardown(mut commands: Commands, query: Query<Entity, With<GameUIRoot>>) {
@@ -280,16 +724,283 @@ fn handle_exit_clicked(
     mut app_state: ResMut<State<AppState>>,
     query: Query<&Interaction, (Changed<Interaction>, With<MenuButton>)>,
 ) {
-    let clicked = query.iter().any(|interaction| match interaction {
-        Interaction::Clicked => true,
-        _ => false,
-    });
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
 
     if clicked {
         app_state.set(AppState::MainMenu).unwrap();
     }
 }
 
+/// Pauses the game from the pause button, the pause keys or when the window
+/// loses focus.
+fn handle_pause(
+    mut app_state: ResMut<State<AppState>>,
+    mut actions: ResMut<ActionState>,
+    mut focus_evr: EventReader<WindowFocused>,
+    query: Query<&Interaction, (Changed<Interaction>, With<PauseButton>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+    let pressed = actions.just_pressed(Action::Menu)
+        || actions.just_pressed(Action::Pause);
+    let focus_lost = focus_evr.iter().any(|event| !event.focused);
+
+    if clicked || pressed || focus_lost {
+        // NOTE: the overlay is entered on the same frame, don't let it react
+        // to the input too.
+        actions.consume(Action::Menu);
+        actions.consume(Action::Pause);
+        // NOTE: another transition, like leaving the game, may already be
+        // queued this frame, in which case it wins over pausing
+        let _ = app_state.push(AppState::Paused);
+    }
+}
+
+fn hide_ui(mut query: Query<&mut Style, With<GameUIRoot>>) {
+    for mut style in query.iter_mut() {
+        style.display = Display::None;
+    }
+}
+
+fn show_ui(mut query: Query<&mut Style, With<GameUIRoot>>) {
+    for mut style in query.iter_mut() {
+        style.display = Display::Flex;
+    }
+}
+
+fn handle_reset_keys(
+    actions: Res<ActionState>,
+    level: Option<Res<LevelState>>,
+    mut evw: EventWriter<ResetLevelEvent>,
+) {
+    // NOTE: a locked level is reset once its outcome is no longer shown
+    let can_reset = level.is_some_and(|level| !level.locked);
+    if can_reset && actions.just_pressed(Action::Reset) {
+        evw.send(ResetLevelEvent);
+    }
+}
+
+fn handle_amount_clicked(
+    mut level: Option<ResMut<LevelState>>,
+    query: Query<&Interaction, (Changed<Interaction>, With<AmountButton>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if let Some(level) = level.as_mut() {
+        if clicked {
+            level.cycle_amount();
+        }
+    }
+}
+
+fn handle_undo_clicked(
+    query: Query<&Interaction, (Changed<Interaction>, With<UndoButton>)>,
+    mut evw: EventWriter<UndoEvent>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        evw.send(UndoEvent);
+    }
+}
+
+fn handle_redo_clicked(
+    query: Query<&Interaction, (Changed<Interaction>, With<RedoButton>)>,
+    mut evw: EventWriter<RedoEvent>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        evw.send(RedoEvent);
+    }
+}
+
+fn handle_undo_keys(
+    actions: Res<ActionState>,
+    mut undo_evw: EventWriter<UndoEvent>,
+    mut redo_evw: EventWriter<RedoEvent>,
+) {
+    if actions.just_pressed(Action::Undo) {
+        undo_evw.send(UndoEvent);
+    }
+    if actions.just_pressed(Action::Redo) {
+        redo_evw.send(RedoEvent);
+    }
+}
+
+fn handle_hint_clicked(
+    query: Query<&Interaction, (Changed<Interaction>, With<HintButton>)>,
+    mut evw: EventWriter<HintEvent>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        evw.send(HintEvent);
+    }
+}
+
+fn handle_hint_keys(
+    actions: Res<ActionState>,
+    mut evw: EventWriter<HintEvent>,
+) {
+    if actions.just_pressed(Action::Hint) {
+        evw.send(HintEvent);
+    }
+}
+
+fn update_hint_indicator(
+    level: Option<Res<LevelState>>,
+    mut given_evr: EventReader<HintGivenEvent>,
+    mut start_evr: EventReader<StartLevelEvent>,
+    mut query: Query<&mut GameIndicator, With<HintIndicator>>,
+) {
+    let started = start_evr.iter().count() > 0;
+    let hint_text = match (given_evr.iter().last(), level) {
+        (Some(HintGivenEvent(hint)), Some(level)) => match hint {
+            Hint::Reveal(index) => level
+                .palette
+                .get(*index)
+                .map_or("-".to_string(), |entry| format!("Use {}", entry.name)),
+            Hint::OnTrack => "On track".into(),
+            Hint::OffTrack => "Off track".into(),
+            Hint::Unavailable => "Not enough lives".into(),
+        },
+        _ if started => "-".into(),
+        _ => return,
+    };
+
+    for mut indicator in query.iter_mut() {
+        if indicator.value != hint_text {
+            indicator.value = hint_text.clone();
+        }
+    }
+}
+
+fn update_hint_markers(
+    level: Option<Res<LevelState>>,
+    brush_query: Query<(&ColorSelector, &Children)>,
+    mut marker_query: Query<&mut Style, With<HintMarker>>,
+) {
+    let level = match level {
+        Some(level) => level,
+        None => return,
+    };
+    if !level.is_changed() {
+        return;
+    }
+
+    for (selector, children) in brush_query.iter() {
+        let revealed = level.revealed.contains(&selector.index);
+        for child in children.iter() {
+            if let Ok(mut style) = marker_query.get_mut(*child) {
+                if style.display != display(revealed) {
+                    style.display = display(revealed);
+                }
+            }
+        }
+    }
+}
+
+fn handle_submit_clicked(
+    query: Query<&Interaction, (Changed<Interaction>, With<SubmitButton>)>,
+    mut evw: EventWriter<SubmitEvent>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        evw.send(SubmitEvent);
+    }
+}
+
+fn handle_submit_keys(
+    actions: Res<ActionState>,
+    settings: Res<Settings>,
+    mut evw: EventWriter<SubmitEvent>,
+) {
+    if settings.submit_mix && actions.just_pressed(Action::Submit) {
+        evw.send(SubmitEvent);
+    }
+}
+
+fn submit_display(settings: &Settings) -> Display {
+    match settings.submit_mix {
+        true => Display::Flex,
+        false => Display::None,
+    }
+}
+
+fn complexity_label(settings: &Settings) -> &'static str {
+    // NOTE: when mixes are submitted, the complexity is only a hint
+    match settings.submit_mix {
+        true => "Suggested complexity",
+        false => "Level complexity",
+    }
+}
+
+fn apply_submit_setting(
+    settings: Res<Settings>,
+    mut slot_query: Query<&mut Style, With<SubmitSlot>>,
+    mut indicator_query: Query<&mut GameIndicator, With<ComplexityIndicator>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut style in slot_query.iter_mut() {
+        style.display = submit_display(&settings);
+    }
+    for mut indicator in indicator_query.iter_mut() {
+        let label = complexity_label(&settings);
+        if indicator.label != label {
+            indicator.label = label.into();
+        }
+    }
+}
+
+fn update_amount_button(
+    level: Option<Res<LevelState>>,
+    mut query: Query<(&mut GameButton, &mut Style), With<AmountButton>>,
+) {
+    if let Some(level) = level {
+        if !level.is_changed() {
+            return;
+        }
+
+        // NOTE: only levels with more than one amount let the player choose
+        let display = match level.amounts.len() > 1 {
+            true => Display::Flex,
+            false => Display::None,
+        };
+        let text = level.amount().map_or(String::new(), |amount| {
+            format!("{} ({}x)", amount.name, amount.parts)
+        });
+
+        for (mut button, mut style) in query.iter_mut() {
+            if style.display != display {
+                style.display = display;
+            }
+            if button.text != text {
+                button.text = text.clone();
+            }
+        }
+    }
+}
+
+// NOTE: the query filters are spelled out so that only brush buttons react
+#[allow(clippy::type_complexity)]
 fn handle_color_clicked(
     interaction_query: Query<
         (&Interaction, &ColorSelector),
@@ -299,36 +1010,149 @@ fn handle_color_clicked(
     mut evw: EventWriter<PlayerColorsChanged>,
     asset_server: Res<AssetServer>,
     audio: Res<Audio>,
-    options: Res<GameOptions>,
+    settings: Res<Settings>,
 ) {
     if let Some(board) = board.as_mut() {
         for (interaction, color_selection) in &interaction_query {
-            match *interaction {
-                Interaction::Clicked => {
-                    board.selected_colors.push(color_selection.color);
-                    evw.send(PlayerColorsChanged);
+            if *interaction == Interaction::Clicked {
+                board.select(color_selection.index);
+                evw.send(PlayerColorsChanged);
 
-                    if !options.mute {
-                        audio.play(asset_server.load("audio/click.ogg"));
-                    }
+                play_effect(
+                    &audio,
+                    &asset_server,
+                    &settings,
+                    "audio/click.ogg",
+                );
+            }
+        }
+    }
+}
+
+// NOTE: candidate swatches are buttons of their own, apart from the brushes
+#[allow(clippy::type_complexity)]
+fn handle_candidate_clicked(
+    interaction_query: Query<
+        (&Interaction, &CandidateSelector),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut board: Option<ResMut<LevelState>>,
+    mut evw: EventWriter<PlayerColorsChanged>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+) {
+    if let Some(board) = board.as_mut() {
+        for (interaction, candidate) in &interaction_query {
+            if *interaction == Interaction::Clicked {
+                board.pick(candidate.index);
+                evw.send(PlayerColorsChanged);
+
+                play_effect(
+                    &audio,
+                    &asset_server,
+                    &settings,
+                    "audio/click.ogg",
+                );
+            }
+        }
+    }
+}
+
+fn handle_color_keys(
+    actions: Res<ActionState>,
+    mut board: Option<ResMut<LevelState>>,
+    mut evw: EventWriter<PlayerColorsChanged>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+) {
+    if let Some(board) = board.as_mut() {
+        if board.locked {
+            return;
+        }
+
+        // NOTE: in reverse puzzles, brush keys pick swatches instead
+        let count = match &board.reverse {
+            Some(puzzle) => puzzle.candidates.len(),
+            None => board.palette.len(),
+        };
+        for index in 0..count {
+            if actions.just_pressed(Action::SelectColor(index)) {
+                match board.reverse.is_some() {
+                    true => board.pick(index),
+                    false => board.select(index),
                 }
-                _ => (),
+                evw.send(PlayerColorsChanged);
+
+                play_effect(
+                    &audio,
+                    &asset_server,
+                    &settings,
+                    "audio/click.ogg",
+                );
             }
         }
     }
 }
 
+// NOTE: only brush buttons grow when they gain focus
+#[allow(clippy::type_complexity)]
+fn highlight_focused_brush(
+    mut query: Query<
+        (&Focusable, &mut Style),
+        (With<ColorSelector>, Changed<Focusable>),
+    >,
+) {
+    for (focusable, mut style) in query.iter_mut() {
+        style.size = match focusable.focused {
+            true => Size::new(Val::Px(220.0), Val::Px(77.0)),
+            false => Size::new(Val::Px(200.0), Val::Px(70.0)),
+        };
+    }
+}
+
+fn update_candidates(
+    settings: Res<Settings>,
+    level: Option<Res<LevelState>>,
+    mut query: Query<(&CandidateSelector, &mut Style, &mut UiColor)>,
+) {
+    let level = match level {
+        Some(level) => level,
+        None => return,
+    };
+    let puzzle = match &level.reverse {
+        Some(puzzle) => puzzle,
+        None => return,
+    };
+    if !level.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for (candidate, mut style, mut ui_color) in query.iter_mut() {
+        style.size = match puzzle.picked == Some(candidate.index) {
+            true => Size::new(Val::Px(130.0), Val::Px(130.0)),
+            false => Size::new(Val::Px(110.0), Val::Px(110.0)),
+        };
+        if let Some(color) = puzzle.candidates.get(candidate.index) {
+            ui_color.0 = simulate_color(*color, settings.color_vision);
+        }
+    }
+}
+
 fn update_player_color(
     mut player_color_query: Query<&mut UiColor, With<PlayerColor>>,
     level: Option<Res<LevelState>>,
+    settings: Res<Settings>,
 ) {
     if let Some(level) = level {
-        if !level.is_changed() {
+        if !level.is_changed() && !settings.is_changed() {
             return;
         }
 
         for mut ui_color in player_color_query.iter_mut() {
-            let new_color = mix_colors(&level.selected_colors);
+            let new_color =
+                simulate_color(level.selected_color(), settings.color_vision);
             if ui_color.0 != new_color {
                 ui_color.0 = new_color
             }
@@ -338,14 +1162,111 @@ fn update_player_color(
 
 fn update_objective_color(
     level: Option<Res<LevelState>>,
+    settings: Res<Settings>,
     mut start_evr: EventReader<StartLevelEvent>,
     mut query: Query<&mut UiColor, With<ObjectiveColor>>,
 ) {
-    for _ in start_evr.iter() {
-        if let Some(ref level) = level {
-            let new_color = mix_colors(&level.objective_colors);
-            for mut color in query.iter_mut() {
-                color.0 = new_color;
+    let started = start_evr.iter().count() > 0;
+    if !started && !settings.is_changed() {
+        return;
+    }
+
+    if let Some(ref level) = level {
+        // NOTE: the color is the answer of reverse puzzles
+        let new_color = match level.reverse {
+            Some(_) => Color::NONE,
+            None => {
+                simulate_color(level.objective_color(), settings.color_vision)
+            }
+        };
+        for mut color in query.iter_mut() {
+            color.0 = new_color;
+        }
+    }
+}
+
+/// Applies changes to the accessibility settings to the brushes already on
+/// screen.
+fn update_brush_colors(
+    settings: Res<Settings>,
+    level: Option<Res<LevelState>>,
+    mut brush_query: Query<(&ColorSelector, &mut UiColor, &Children)>,
+    mut label_query: Query<(&mut Style, &mut Text), With<BrushLabel>>,
+) {
+    let level = match level {
+        Some(level) => level,
+        None => return,
+    };
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (selector, mut ui_color, children) in brush_query.iter_mut() {
+        let color = match level.palette.get(selector.index) {
+            Some(entry) => simulate_color(entry.color, settings.color_vision),
+            None => continue,
+        };
+        ui_color.0 = color;
+
+        for child in children.iter() {
+            if let Ok((mut style, mut text)) = label_query.get_mut(*child) {
+                style.display = display(settings.colorblind);
+                text.sections[0].style.color = contrasting_text(color);
+            }
+        }
+    }
+}
+
+fn update_color_readouts(
+    settings: Res<Settings>,
+    level: Option<Res<LevelState>>,
+    objective_query: Query<&Children, With<ObjectiveColor>>,
+    player_query: Query<&Children, With<PlayerColor>>,
+    mut readout_query: Query<(&mut Style, &mut Text), With<ColorReadout>>,
+) {
+    let level = match level {
+        Some(level) => level,
+        None => return,
+    };
+    if !level.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    // NOTE: readouts describe the actual colors, not the simulated ones
+    let objective_color = level.objective_color();
+    let player_color = level.selected_color();
+    // NOTE: reverse puzzles show their recipe instead, which is the question
+    let objective = match level.reverse {
+        Some(_) => {
+            (level.objective.describe(&level.palette), Color::NONE, true)
+        }
+        None => (
+            describe_color(objective_color),
+            simulate_color(objective_color, settings.color_vision),
+            settings.colorblind,
+        ),
+    };
+    let player = match level.has_selection() {
+        false => ("-".to_string(), Color::NONE, settings.colorblind),
+        true => (
+            describe_color(player_color),
+            simulate_color(player_color, settings.color_vision),
+            settings.colorblind,
+        ),
+    };
+    let panels = objective_query
+        .iter()
+        .map(|children| (children, &objective))
+        .chain(player_query.iter().map(|children| (children, &player)));
+
+    for (children, (description, background, shown)) in panels {
+        for child in children.iter() {
+            if let Ok((mut style, mut text)) = readout_query.get_mut(*child) {
+                style.display = display(*shown);
+                text.sections[0].style.color = contrasting_text(*background);
+                if text.sections[0].value != *description {
+                    text.sections[0].value = description.clone();
+                }
             }
         }
     }
@@ -357,7 +1278,7 @@ fn update_complexity_indicator(
 ) {
     if let Some(level) = level {
         for mut indicator in query.iter_mut() {
-            let new_value = level.objective_colors.len().to_string();
+            let new_value = level.complexity.to_string();
             if indicator.value != new_value {
                 indicator.value = new_value;
             }
@@ -371,7 +1292,7 @@ fn update_selection_indicator(
 ) {
     if let Some(level) = level {
         for mut indicator in query.iter_mut() {
-            let new_value = level.selected_colors.len().to_string();
+            let new_value = level.clicks.to_string();
             if indicator.value != new_value {
                 indicator.value = new_value;
             }
@@ -379,14 +1300,48 @@ fn update_selection_indicator(
     }
 }
 
+fn update_closeness_indicator(
+    mut query: Query<&mut GameIndicator, With<ClosenessIndicator>>,
+    level: Option<Res<LevelState>>,
+) {
+    if let Some(level) = level {
+        if !level.is_changed() {
+            return;
+        }
+
+        let new_value = match level.has_selection() {
+            false => "-".to_string(),
+            true => format!("{:.0}%", level.closeness() * 100.0),
+        };
+        for mut indicator in query.iter_mut() {
+            if indicator.value != new_value {
+                indicator.value = new_value.clone();
+            }
+        }
+    }
+}
+
 fn update_level_indicator(
     mut query: Query<&mut GameIndicator, With<LevelIndicator>>,
     level: Option<Res<LevelState>>,
+    mode: Res<GameMode>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
 ) {
+    let level_count = packs
+        .get(&active_pack.0)
+        .and_then(|pack| mode.level_count(pack));
+
     for mut indicator in query.iter_mut() {
-        let level_text = level.as_ref().map_or("-".to_string(), |level| {
-            format!("{}/{}", level.level_index + 1, OBJECTIVES_DATA.len())
-        });
+        let level_text =
+            level
+                .as_ref()
+                .map_or("-".to_string(), |level| match level_count {
+                    Some(count) => {
+                        format!("{}/{}", level.level_index + 1, count)
+                    }
+                    None => (level.level_index + 1).to_string(),
+                });
 
         if indicator.value != level_text {
             indicator.value = level_text;
@@ -396,11 +1351,13 @@ fn update_level_indicator(
 
 fn update_lives_indicator(
     game: Res<GameState>,
+    rules: Res<DifficultyRules>,
     mut query: Query<&mut GameIndicator, With<LivesIndicator>>,
 ) {
     if game.is_changed() {
         for mut indicator in query.iter_mut() {
-            let lives_text = format!("{}/{}", game.lives_remaining, 3);
+            let lives_text =
+                format!("{}/{}", game.lives_remaining, rules.max_lives);
             if indicator.value != lives_text {
                 indicator.value = lives_text;
             }
@@ -408,6 +1365,37 @@ fn update_lives_indicator(
     }
 }
 
+fn update_score_indicator(
+    game: Res<GameState>,
+    mut query: Query<&mut GameIndicator, With<ScoreIndicator>>,
+) {
+    if game.is_changed() {
+        for mut indicator in query.iter_mut() {
+            let score_text = game.score.to_string();
+            if indicator.value != score_text {
+                indicator.value = score_text;
+            }
+        }
+    }
+}
+
+fn update_time_indicator(
+    clock: Option<Res<LevelClock>>,
+    mut query: Query<&mut GameIndicator, With<TimeIndicator>>,
+) {
+    let time_text = clock
+        .and_then(|clock| clock.time_left)
+        .map_or("-".to_string(), |time_left| {
+            format!("{:.0}s", time_left.ceil())
+        });
+
+    for mut indicator in query.iter_mut() {
+        if indicator.value != time_text {
+            indicator.value = time_text.clone();
+        }
+    }
+}
+
 fn show_alert(
     mut alert_evr: EventReader<AlertStartedEvent>,
     mut visiblity_query: Query<(&mut Style, &AlertVisibility)>,
@@ -428,6 +1416,7 @@ fn show_alert(
                 crate::game::AlertType::LevelSucceeded => Color::GREEN,
                 crate::game::AlertType::GameLost => Color::RED,
                 crate::game::AlertType::GameWon => Color::GREEN,
+                crate::game::AlertType::TimeUp => Color::ORANGE,
             };
         }
     }
@@ -0,0 +1,424 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::StorageBackend;
+
+const INPUT_MAP_KEY: &str = "input_map";
+
+/// Number of palette slots that can be bound to an input.
+pub const COLOR_SLOT_COUNT: usize = 9;
+
+// NOTE: these gamepad buttons move and press the focus, see
+// `widgets::FocusPlugin`.
+const RESERVED_BUTTONS: [GamepadButtonType; 5] = [
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+    GamepadButtonType::South,
+];
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .add_startup_system(load_input_map)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_action_state.after(InputSystem),
+            );
+    }
+}
+
+/// Something the player can do with a key or gamepad button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    /// Adds the brush at this index of the palette to the mix.
+    SelectColor(usize),
+    Undo,
+    Redo,
+    Submit,
+    Hint,
+    Reset,
+    Menu,
+    Pause,
+}
+
+impl Action {
+    /// Every action, in the order they are shown in the settings.
+    pub fn all() -> impl Iterator<Item = Action> {
+        (0..COLOR_SLOT_COUNT).map(Action::SelectColor).chain([
+            Action::Undo,
+            Action::Redo,
+            Action::Submit,
+            Action::Hint,
+            Action::Reset,
+            Action::Menu,
+            Action::Pause,
+        ])
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Action::SelectColor(index) => format!("Brush {}", index + 1),
+            Action::Undo => "Undo".into(),
+            Action::Redo => "Redo".into(),
+            Action::Submit => "Submit".into(),
+            Action::Hint => "Hint".into(),
+            Action::Reset => "Reset".into(),
+            Action::Menu => "Menu".into(),
+            Action::Pause => "Pause".into(),
+        }
+    }
+}
+
+/// An input that triggers an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Gamepad(GamepadButtonType),
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Gamepad(button) => write!(f, "Pad {:?}", button),
+        }
+    }
+}
+
+/// The key and gamepad button bound to an action.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct ActionBindings {
+    pub key: Option<KeyCode>,
+    pub gamepad: Option<GamepadButtonType>,
+}
+
+impl ActionBindings {
+    fn new(key: KeyCode, gamepad: GamepadButtonType) -> Self {
+        Self {
+            key: Some(key),
+            gamepad: Some(gamepad),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Binding> {
+        let key = self.key.map(Binding::Key);
+        let gamepad = self.gamepad.map(Binding::Gamepad);
+        key.into_iter().chain(gamepad)
+    }
+}
+
+impl std::fmt::Display for ActionBindings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bindings: Vec<String> =
+            self.iter().map(|binding| binding.to_string()).collect();
+        match bindings.is_empty() {
+            true => write!(f, "-"),
+            false => write!(f, "{}", bindings.join(", ")),
+        }
+    }
+}
+
+/// Why a binding was refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingConflict {
+    /// Already bound to another action.
+    Action(Action),
+    /// Used to navigate the menus.
+    Reserved,
+}
+
+impl std::fmt::Display for BindingConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingConflict::Action(action) => {
+                write!(f, "already bound to {}", action.name())
+            }
+            BindingConflict::Reserved => write!(f, "reserved for navigation"),
+        }
+    }
+}
+
+/// Maps actions to the inputs that trigger them. Saved to storage whenever
+/// it is changed from the settings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: HashMap<Action, ActionBindings>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        const COLOR_KEYS: [KeyCode; COLOR_SLOT_COUNT] = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+
+        let colors = COLOR_KEYS.iter().enumerate().map(|(index, key)| {
+            let bindings = ActionBindings {
+                key: Some(*key),
+                gamepad: None,
+            };
+            (Action::SelectColor(index), bindings)
+        });
+        let bindings = colors
+            .chain([
+                (
+                    Action::Undo,
+                    ActionBindings::new(KeyCode::Z, GamepadButtonType::West),
+                ),
+                (
+                    Action::Redo,
+                    ActionBindings::new(KeyCode::Y, GamepadButtonType::North),
+                ),
+                (
+                    Action::Submit,
+                    ActionBindings::new(
+                        KeyCode::Return,
+                        GamepadButtonType::RightTrigger,
+                    ),
+                ),
+                (
+                    Action::Hint,
+                    ActionBindings::new(KeyCode::H, GamepadButtonType::East),
+                ),
+                (
+                    Action::Reset,
+                    ActionBindings::new(
+                        KeyCode::Back,
+                        GamepadButtonType::LeftTrigger,
+                    ),
+                ),
+                (
+                    Action::Menu,
+                    ActionBindings::new(
+                        KeyCode::Escape,
+                        GamepadButtonType::Select,
+                    ),
+                ),
+                (
+                    Action::Pause,
+                    ActionBindings::new(KeyCode::P, GamepadButtonType::Start),
+                ),
+            ])
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> ActionBindings {
+        self.bindings.get(&action).copied().unwrap_or_default()
+    }
+
+    /// The action that `binding` triggers, if any.
+    pub fn action(&self, binding: Binding) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, bindings)| bindings.iter().any(|b| b == binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Binds `binding` to `action`, replacing its previous key or gamepad
+    /// button. Inputs can only trigger one action.
+    pub fn bind(
+        &mut self,
+        action: Action,
+        binding: Binding,
+    ) -> Result<(), BindingConflict> {
+        if let Binding::Gamepad(button) = binding {
+            if RESERVED_BUTTONS.contains(&button) {
+                return Err(BindingConflict::Reserved);
+            }
+        }
+        match self.action(binding) {
+            Some(other) if other != action => {
+                return Err(BindingConflict::Action(other))
+            }
+            _ => (),
+        }
+
+        let bindings = self.bindings.entry(action).or_default();
+        match binding {
+            Binding::Key(key) => bindings.key = Some(key),
+            Binding::Gamepad(button) => bindings.gamepad = Some(button),
+        }
+        Ok(())
+    }
+
+    /// Gives the default bindings to actions that have none, as long as they
+    /// aren't already used. Maps saved before an action was added lack it.
+    fn with_defaults(mut self) -> Self {
+        for (action, defaults) in InputMap::default().bindings {
+            if self.bindings.contains_key(&action) {
+                continue;
+            }
+            for binding in defaults.iter() {
+                // NOTE: refused bindings are left for the player to pick
+                let _ = self.bind(action, binding);
+            }
+        }
+        self
+    }
+}
+
+/// Actions triggered this frame, read from the [`InputMap`].
+#[derive(Default)]
+pub struct ActionState {
+    just_pressed: HashSet<Action>,
+}
+
+impl ActionState {
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Stops reporting `action` for the rest of the frame, so that systems
+    /// of a state entered on the same frame don't react to it too.
+    pub fn consume(&mut self, action: Action) {
+        self.just_pressed.remove(&action);
+    }
+}
+
+fn load_input_map(
+    mut commands: Commands,
+    storage: Option<Res<StorageBackend>>,
+) {
+    let input_map = storage
+        .and_then(|storage| storage.load::<InputMap>(INPUT_MAP_KEY))
+        .map(InputMap::with_defaults)
+        .unwrap_or_default();
+    commands.insert_resource::<InputMap>(input_map);
+}
+
+pub fn save_input_map(storage: Option<&StorageBackend>, input_map: &InputMap) {
+    if let Some(storage) = storage {
+        storage.save(INPUT_MAP_KEY, input_map);
+    }
+}
+
+fn update_action_state(
+    input_map: Option<Res<InputMap>>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut state: ResMut<ActionState>,
+) {
+    state.just_pressed.clear();
+
+    let input_map = match input_map {
+        Some(input_map) => input_map,
+        None => return,
+    };
+
+    let pressed_keys = keys.get_just_pressed().copied().map(Binding::Key);
+    let pressed_buttons = buttons
+        .get_just_pressed()
+        .map(|button| Binding::Gamepad(button.button_type));
+    for binding in pressed_keys.chain(pressed_buttons) {
+        if let Some(action) = input_map.action(binding) {
+            state.just_pressed.insert(action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_dont_conflict() {
+        let input_map = InputMap::default();
+        let mut used = HashSet::new();
+        for action in Action::all() {
+            let bindings = input_map.bindings(action);
+            assert!(bindings.key.is_some(), "{} has no key", action.name());
+            for binding in bindings.iter() {
+                assert!(used.insert(binding), "{} is bound twice", binding);
+                assert_eq!(input_map.action(binding), Some(action));
+                if let Binding::Gamepad(button) = binding {
+                    assert!(!RESERVED_BUTTONS.contains(&button));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn inputs_trigger_only_one_action() {
+        let mut input_map = InputMap::default();
+
+        assert_eq!(
+            input_map.bind(Action::Hint, Binding::Key(KeyCode::Z)),
+            Err(BindingConflict::Action(Action::Undo))
+        );
+        assert_eq!(input_map.bindings(Action::Hint).key, Some(KeyCode::H));
+        assert_eq!(
+            input_map.action(Binding::Key(KeyCode::Z)),
+            Some(Action::Undo)
+        );
+
+        // NOTE: binding an action to its own input again is harmless
+        assert_eq!(
+            input_map.bind(Action::Undo, Binding::Key(KeyCode::Z)),
+            Ok(())
+        );
+
+        assert_eq!(
+            input_map.bind(Action::Hint, Binding::Key(KeyCode::Q)),
+            Ok(())
+        );
+        assert_eq!(input_map.action(Binding::Key(KeyCode::H)), None);
+        assert_eq!(
+            input_map.action(Binding::Key(KeyCode::Q)),
+            Some(Action::Hint)
+        );
+    }
+
+    #[test]
+    fn navigation_buttons_cant_be_bound() {
+        let mut input_map = InputMap::default();
+        for button in RESERVED_BUTTONS {
+            assert_eq!(
+                input_map.bind(Action::Submit, Binding::Gamepad(button)),
+                Err(BindingConflict::Reserved)
+            );
+        }
+        assert_eq!(
+            input_map.bindings(Action::Submit).gamepad,
+            Some(GamepadButtonType::RightTrigger)
+        );
+    }
+
+    #[test]
+    fn missing_actions_get_unused_defaults() {
+        let mut input_map = InputMap {
+            bindings: HashMap::new(),
+        };
+        input_map
+            .bind(Action::Undo, Binding::Key(KeyCode::H))
+            .unwrap();
+
+        let input_map = input_map.with_defaults();
+
+        assert_eq!(input_map.bindings(Action::Undo).key, Some(KeyCode::H));
+        assert_eq!(input_map.bindings(Action::Hint).key, None);
+        assert_eq!(
+            input_map.bindings(Action::Hint).gamepad,
+            Some(GamepadButtonType::East)
+        );
+        assert_eq!(input_map.bindings(Action::Redo).key, Some(KeyCode::Y));
+    }
+}
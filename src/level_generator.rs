@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use crate::{
+    color_mixer::MixingModelKind,
+    level_pack::{BrushAmount, LevelDefinition, PaletteColor},
+    recipe::Recipe,
+};
+
+/// Complexity of the first generated level.
+pub const MIN_COMPLEXITY: usize = 2;
+
+/// Complexity at which the difficulty curve stops rising.
+pub const MAX_COMPLEXITY: usize = 8;
+
+/// Number of levels generated at each complexity before moving to the next.
+pub const LEVELS_PER_COMPLEXITY: u32 = 5;
+
+// NOTE: two mixes closer than this (sum of absolute channel differences) can't
+// be told apart on screen, which makes the recipe ambiguous.
+const AMBIGUITY_THRESHOLD: f32 = 0.015;
+
+const MAX_ATTEMPTS: usize = 100;
+
+/// Produces random, unambiguous recipes from a palette.
+///
+/// The same seed and palette always produce the same sequence of levels.
+pub struct LevelGenerator {
+    seed: u64,
+    rng: Pcg32,
+    palette: Vec<PaletteColor>,
+    mixing: MixingModelKind,
+    // mixed colors of every recipe of a given size, by size
+    mixes: HashMap<usize, Vec<(Recipe, Color)>>,
+}
+
+impl LevelGenerator {
+    pub fn new(
+        seed: u64,
+        palette: Vec<PaletteColor>,
+        mixing: MixingModelKind,
+    ) -> Self {
+        Self {
+            seed,
+            rng: Pcg32::seed_from_u64(seed),
+            palette,
+            mixing,
+            mixes: default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Goes back to the first level of the sequence.
+    pub fn restart(&mut self) {
+        self.rng = Pcg32::seed_from_u64(self.seed);
+    }
+
+    /// Complexity that the level at `level_index` should have, rising by one
+    /// every few levels like the handwritten campaign does.
+    pub fn target_complexity(level_index: u32) -> usize {
+        let complexity =
+            MIN_COMPLEXITY + (level_index / LEVELS_PER_COMPLEXITY) as usize;
+        usize::min(complexity, MAX_COMPLEXITY)
+    }
+
+    /// Generates the level at `level_index` on the difficulty curve.
+    pub fn generate_level(
+        &mut self,
+        level_index: u32,
+    ) -> Option<LevelDefinition> {
+        self.generate_up_to(Self::target_complexity(level_index))
+    }
+
+    /// Generates a level of `size` colors, or of fewer colors when no
+    /// acceptable recipe of that size could be found. Returns `None` when no
+    /// size works, which only happens with palettes of fewer than 2 colors.
+    pub fn generate_up_to(&mut self, size: usize) -> Option<LevelDefinition> {
+        (MIN_COMPLEXITY..=size).rev().find_map(|size| {
+            let level = self.generate(size);
+            if level.is_none() {
+                warn!("no unambiguous recipe of {} colors was found", size);
+            }
+            level
+        })
+    }
+
+    /// Generates a level whose recipe uses exactly `size` colors, or `None`
+    /// if no acceptable recipe could be found.
+    pub fn generate(&mut self, size: usize) -> Option<LevelDefinition> {
+        if size < 2 || self.palette.len() < 2 {
+            return None;
+        }
+
+        for _ in 0..MAX_ATTEMPTS {
+            let recipe: Recipe = (0..size)
+                .map(|_| self.rng.gen_range(0..self.palette.len()))
+                .collect();
+
+            // NOTE: reducible recipes, which include the trivial ones using a
+            // single color, would advertise a misleading complexity.
+            if recipe.is_reducible() || self.is_ambiguous(&recipe) {
+                continue;
+            }
+
+            return Some(LevelDefinition {
+                palette: self.palette.clone(),
+                objective: recipe,
+                matching: default(),
+                amounts: vec![BrushAmount::drop()],
+            });
+        }
+
+        None
+    }
+
+    /// Whether another recipe of the same size mixes into a color that looks
+    /// the same as this one.
+    fn is_ambiguous(&mut self, recipe: &Recipe) -> bool {
+        let target = recipe.mix(&self.palette, self.mixing.model());
+        self.mixes_of_size(recipe.len() as usize).iter().any(
+            |(other, color)| {
+                other != recipe
+                    && color_difference(*color, target) < AMBIGUITY_THRESHOLD
+            },
+        )
+    }
+
+    fn mixes_of_size(&mut self, size: usize) -> &[(Recipe, Color)] {
+        let palette = &self.palette;
+        let model = self.mixing.model();
+        self.mixes.entry(size).or_insert_with(|| {
+            multisets(palette.len(), size)
+                .into_iter()
+                .map(|indices| {
+                    let recipe = Recipe::from_indices(indices);
+                    let color = recipe.mix(palette, model);
+                    (recipe, color)
+                })
+                .collect()
+        })
+    }
+}
+
+fn color_difference(a: Color, b: Color) -> f32 {
+    f32::abs(a.r() - b.r()) + f32::abs(a.g() - b.g()) + f32::abs(a.b() - b.b())
+}
+
+/// Lists every sorted multiset of `size` indices taken from `0..count`.
+pub(crate) fn multisets(count: usize, size: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    let mut current = Vec::with_capacity(size);
+    push_multisets(count, size, 0, &mut current, &mut result);
+    result
+}
+
+fn push_multisets(
+    count: usize,
+    size: usize,
+    start: usize,
+    current: &mut Vec<usize>,
+    result: &mut Vec<Vec<usize>>,
+) {
+    if current.len() == size {
+        result.push(current.clone());
+        return;
+    }
+
+    for index in start..count {
+        current.push(index);
+        push_multisets(count, size, index, current, result);
+        current.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL_COUNT: u32 = 30;
+
+    fn palette() -> Vec<PaletteColor> {
+        [
+            ("Red", Color::RED),
+            ("Yellow", Color::YELLOW),
+            ("Blue", Color::BLUE),
+            ("White", Color::WHITE),
+            ("Black", Color::BLACK),
+        ]
+        .into_iter()
+        .map(|(name, color)| PaletteColor {
+            name: name.into(),
+            color,
+        })
+        .collect()
+    }
+
+    fn generator(seed: u64) -> LevelGenerator {
+        LevelGenerator::new(seed, palette(), MixingModelKind::default())
+    }
+
+    fn objectives(generator: &mut LevelGenerator) -> Vec<Recipe> {
+        (0..LEVEL_COUNT)
+            .map(|level_index| {
+                generator
+                    .generate_level(level_index)
+                    .expect("the palette has enough colors")
+                    .objective
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_generates_same_levels() {
+        assert_eq!(
+            objectives(&mut generator(42)),
+            objectives(&mut generator(42))
+        );
+    }
+
+    #[test]
+    fn restart_replays_levels() {
+        let mut generator = generator(7);
+        let first_run = objectives(&mut generator);
+        generator.restart();
+        assert_eq!(objectives(&mut generator), first_run);
+    }
+
+    #[test]
+    fn recipes_use_several_colors_and_never_reduce() {
+        let mut generator = generator(3);
+        for recipe in objectives(&mut generator) {
+            assert!(!recipe.is_reducible(), "{recipe:?} is reducible");
+            assert!(recipe.iter().count() >= 2, "{recipe:?} uses one color");
+        }
+    }
+
+    #[test]
+    fn recipes_are_unambiguous() {
+        let mut generator = generator(11);
+        let model = MixingModelKind::default().model();
+        for recipe in objectives(&mut generator) {
+            let target = recipe.mix(&palette(), model);
+            let size = recipe.len() as usize;
+            for indices in multisets(palette().len(), size) {
+                let other = Recipe::from_indices(indices);
+                let color = other.mix(&palette(), model);
+                assert!(
+                    other == recipe
+                        || color_difference(color, target)
+                            >= AMBIGUITY_THRESHOLD,
+                    "{recipe:?} looks like {other:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn generated_sizes_follow_the_curve() {
+        let mut generator = generator(5);
+        for level_index in 0..LEVEL_COUNT {
+            let level = generator.generate_level(level_index).unwrap();
+            assert_eq!(
+                level.objective.len() as usize,
+                LevelGenerator::target_complexity(level_index)
+            );
+        }
+    }
+
+    #[test]
+    fn too_small_palettes_generate_nothing() {
+        let mut generator = LevelGenerator::new(
+            1,
+            palette().into_iter().take(1).collect(),
+            MixingModelKind::default(),
+        );
+        assert!(generator.generate_level(0).is_none());
+    }
+}
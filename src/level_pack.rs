@@ -0,0 +1,410 @@
+use anyhow::{anyhow, bail};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    color_distance::MatchRule, color_mixer::MixingModelKind,
+    level_generator::MAX_COMPLEXITY, recipe::Recipe, solver::Solver,
+};
+
+pub const DEFAULT_LEVEL_PACK: &str = "levels/default.levels.ron";
+
+pub struct LevelPackPlugin;
+
+impl Plugin for LevelPackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LevelPack>()
+            .init_asset_loader::<LevelPackLoader>()
+            .init_resource::<MixingModelKind>()
+            .add_startup_system(load_level_pack)
+            .add_system(apply_pack_mixing);
+    }
+}
+
+/// The level pack that the game picks its levels from.
+pub struct ActiveLevelPack(pub Handle<LevelPack>);
+
+/// A set of levels that can be shipped as a `.levels.ron` or `.levels.json`
+/// asset without recompiling the game.
+#[derive(Debug, TypeUuid)]
+#[uuid = "5b0f6a3e-2a4c-4f37-9d54-6f1c0e8b7a21"]
+pub struct LevelPack {
+    pub name: String,
+    pub author: String,
+    pub difficulty: u32,
+    /// How the pack's colors mix together.
+    pub mixing: MixingModelKind,
+    pub palette: Vec<PaletteColor>,
+    /// Extra colors that levels can add to their palette.
+    pub pigments: Vec<PaletteColor>,
+    pub levels: Vec<LevelDefinition>,
+}
+
+impl LevelPack {
+    pub fn level(&self, level_index: u32) -> Option<&LevelDefinition> {
+        self.levels.get(level_index as usize)
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PaletteColor {
+    pub name: String,
+    pub color: Color,
+}
+
+/// How much paint a single brush click adds to the mix.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BrushAmount {
+    pub name: String,
+    pub parts: u32,
+}
+
+impl BrushAmount {
+    /// The single-part brush that levels use unless they declare amounts.
+    pub fn drop() -> Self {
+        Self {
+            name: "Drop".into(),
+            parts: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelDefinition {
+    pub palette: Vec<PaletteColor>,
+    /// The recipe to find, as parts of each color of this level's palette.
+    pub objective: Recipe,
+    pub matching: MatchRule,
+    /// Amounts that the player can pick from for each brush click.
+    pub amounts: Vec<BrushAmount>,
+}
+
+impl LevelDefinition {
+    /// Fewest brush clicks needed to make the objective.
+    pub fn complexity(&self) -> u32 {
+        recipe_complexity(&self.objective, &self.amounts)
+    }
+}
+
+pub fn recipe_complexity(recipe: &Recipe, amounts: &[BrushAmount]) -> u32 {
+    let parts: Vec<u32> = amounts.iter().map(|amount| amount.parts).collect();
+    recipe.min_clicks(&parts).unwrap_or_else(|| recipe.len())
+}
+
+// raw representation of the asset, before palette names are resolved
+#[derive(Deserialize)]
+struct LevelPackData {
+    name: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    difficulty: u32,
+    /// How colors mix together in every level. Mixes like paint when
+    /// omitted.
+    #[serde(default)]
+    mixing: MixingModelKind,
+    palette: Vec<PaletteColorData>,
+    /// Extra colors that are not part of the default palette but that levels
+    /// can opt into with their own palette.
+    #[serde(default)]
+    pigments: Vec<PaletteColorData>,
+    /// How mixes are compared to objectives, unless a level overrides it.
+    /// Requires the exact recipe when omitted.
+    #[serde(default)]
+    matching: MatchRule,
+    /// Brush amounts available in every level, unless a level overrides them.
+    /// Each click adds a single part when omitted.
+    #[serde(default)]
+    amounts: Option<Vec<BrushAmount>>,
+    levels: Vec<LevelData>,
+}
+
+#[derive(Deserialize)]
+struct PaletteColorData {
+    name: String,
+    color: String,
+}
+
+#[derive(Deserialize)]
+struct LevelData {
+    /// Names of the colors available in this level, in display order. Uses
+    /// the pack's palette when omitted.
+    #[serde(default)]
+    palette: Option<Vec<String>>,
+    recipe: Vec<RecipeEntry>,
+    #[serde(default)]
+    matching: Option<MatchRule>,
+    #[serde(default)]
+    amounts: Option<Vec<BrushAmount>>,
+}
+
+/// A color of a recipe, either as a single part (`"Blue"`) or as a number of
+/// parts (`("Yellow", 3)`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecipeEntry {
+    Single(String),
+    Parts(String, u32),
+}
+
+impl RecipeEntry {
+    fn name(&self) -> &str {
+        match self {
+            RecipeEntry::Single(name) => name,
+            RecipeEntry::Parts(name, _) => name,
+        }
+    }
+
+    fn parts(&self) -> u32 {
+        match self {
+            RecipeEntry::Single(_) => 1,
+            RecipeEntry::Parts(_, parts) => *parts,
+        }
+    }
+}
+
+fn parse_colors(
+    entries: Vec<PaletteColorData>,
+) -> anyhow::Result<Vec<PaletteColor>> {
+    entries
+        .into_iter()
+        .map(|entry| {
+            let hex = entry.color.trim_start_matches('#');
+            let color = Color::hex(hex).map_err(|err| {
+                anyhow!("invalid color {:?} for {}: {}", hex, entry.name, err)
+            })?;
+            Ok(PaletteColor {
+                name: entry.name,
+                color,
+            })
+        })
+        .collect()
+}
+
+fn find_color<'a>(
+    colors: &'a [PaletteColor],
+    name: &str,
+) -> Option<&'a PaletteColor> {
+    colors.iter().find(|entry| entry.name == name)
+}
+
+impl TryFrom<LevelPackData> for LevelPack {
+    type Error = anyhow::Error;
+
+    fn try_from(data: LevelPackData) -> Result<Self, Self::Error> {
+        let palette = parse_colors(data.palette)?;
+        if palette.is_empty() {
+            bail!("level pack {:?} has an empty palette", data.name);
+        }
+
+        let pigments = parse_colors(data.pigments)?;
+        let mut known_colors = palette.clone();
+        known_colors.extend(pigments.iter().cloned());
+
+        let pack_amounts =
+            data.amounts.unwrap_or_else(|| vec![BrushAmount::drop()]);
+
+        let mut pack_solver = Solver::new(palette.clone(), data.mixing);
+
+        let levels = data
+            .levels
+            .into_iter()
+            .enumerate()
+            .map(|(index, level)| {
+                let has_own_palette = level.palette.is_some();
+                let level_palette = match level.palette {
+                    Some(names) => names
+                        .iter()
+                        .map(|name| {
+                            find_color(&known_colors, name).cloned().ok_or_else(
+                                || {
+                                    anyhow!(
+                                        "level {} declares unknown color {:?}",
+                                        index + 1,
+                                        name
+                                    )
+                                },
+                            )
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?,
+                    None => palette.clone(),
+                };
+
+                if level_palette.is_empty() {
+                    bail!("level {} has an empty palette", index + 1);
+                }
+
+                if level.recipe.is_empty() {
+                    bail!("level {} has an empty recipe", index + 1);
+                }
+
+                let mut objective = Recipe::new();
+                for entry in level.recipe.iter() {
+                    let color_index = level_palette
+                        .iter()
+                        .position(|color| color.name == entry.name())
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "level {} uses color {:?} which is not in its \
+                                 palette",
+                                index + 1,
+                                entry.name()
+                            )
+                        })?;
+                    objective.add_parts(color_index, entry.parts());
+                }
+
+                if objective.is_empty() {
+                    bail!("level {} has an empty recipe", index + 1);
+                }
+
+                let amounts =
+                    level.amounts.unwrap_or_else(|| pack_amounts.clone());
+                if amounts.is_empty() || amounts.iter().any(|a| a.parts == 0) {
+                    bail!("level {} has an invalid brush amount", index + 1);
+                }
+
+                let parts: Vec<u32> = amounts.iter().map(|a| a.parts).collect();
+                let complexity = match objective.min_clicks(&parts) {
+                    Some(complexity) => complexity,
+                    None => bail!(
+                        "level {} can't be made with its brush amounts",
+                        index + 1
+                    ),
+                };
+
+                let reduced_complexity =
+                    recipe_complexity(&objective.reduced(), &amounts);
+                if reduced_complexity < complexity {
+                    warn!(
+                        "level {} has a complexity of {} but its recipe \
+                         reduces to a complexity of {}",
+                        index + 1,
+                        complexity,
+                        reduced_complexity
+                    );
+                }
+
+                let matching = level.matching.unwrap_or(data.matching);
+                // NOTE: checking bigger recipes would slow down loading too
+                // much, and generated levels never go past this size either.
+                if objective.len() as usize <= MAX_COMPLEXITY {
+                    let mut level_solver = has_own_palette.then(|| {
+                        Solver::new(level_palette.clone(), data.mixing)
+                    });
+                    let solver =
+                        level_solver.as_mut().unwrap_or(&mut pack_solver);
+                    let target =
+                        objective.mix(&level_palette, data.mixing.model());
+                    let others = solver
+                        .solve(target, objective.len() as usize, &matching)
+                        .into_iter()
+                        .filter(|solution| {
+                            !solution.recipe.equivalent(&objective)
+                        })
+                        .count();
+                    if others > 0 {
+                        warn!(
+                            "level {} can also be solved with {} other \
+                             recipe(s)",
+                            index + 1,
+                            others
+                        );
+                    }
+                }
+
+                Ok(LevelDefinition {
+                    palette: level_palette,
+                    objective,
+                    matching,
+                    amounts,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if levels.is_empty() {
+            bail!("level pack {:?} has no levels", data.name);
+        }
+
+        Ok(LevelPack {
+            name: data.name,
+            author: data.author,
+            difficulty: data.difficulty,
+            mixing: data.mixing,
+            palette,
+            pigments,
+            levels,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct LevelPackLoader;
+
+impl AssetLoader for LevelPackLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let extension = load_context.path().extension();
+            let data: LevelPackData = match extension.and_then(|e| e.to_str()) {
+                Some("json") => serde_json::from_slice(bytes)?,
+                _ => ron::de::from_bytes(bytes)?,
+            };
+
+            let pack = LevelPack::try_from(data)?;
+            info!(
+                "Loaded level pack {:?} by {:?} ({} levels, difficulty {})",
+                pack.name,
+                pack.author,
+                pack.levels.len(),
+                pack.difficulty
+            );
+            load_context.set_default_asset(LoadedAsset::new(pack));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["levels.ron", "levels.json"]
+    }
+}
+
+fn load_level_pack(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ActiveLevelPack(
+        asset_server.load(DEFAULT_LEVEL_PACK),
+    ));
+}
+
+/// Mixes colors the way the active pack asks for, once it is loaded.
+fn apply_pack_mixing(
+    mut mixing: ResMut<MixingModelKind>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+    mut asset_evr: EventReader<AssetEvent<LevelPack>>,
+) {
+    for event in asset_evr.iter() {
+        match event {
+            AssetEvent::Created { handle }
+            | AssetEvent::Modified { handle }
+                if *handle == active_pack.0 =>
+            {
+                if let Some(pack) = packs.get(handle) {
+                    *mixing = pack.mixing;
+                }
+            }
+            _ => {}
+        }
+    }
+}
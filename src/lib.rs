@@ -1,9 +1,27 @@
 use bevy::prelude::*;
 
+mod color_distance;
 mod color_mixer;
-mod main_menu;
-mod game_ui;
+mod color_vision;
+mod daily;
 mod game;
+mod game_ui;
+mod input;
+pub mod level_generator;
+mod level_pack;
+mod main_menu;
+mod pause;
+mod recipe;
+mod recipe_finder;
+mod reverse;
+mod sandbox;
+mod save;
+mod settings;
+mod settings_menu;
+mod solver;
+mod stats;
+mod stats_menu;
+pub mod storage;
 mod widgets;
 
 pub const LAUNCHER_TITLE: &str = "Guess Hue?";
@@ -12,6 +30,11 @@ pub const LAUNCHER_TITLE: &str = "Guess Hue?";
 enum AppState {
     MainMenu,
     InGame,
+    Settings,
+    Stats,
+    Paused,
+    RecipeFinder,
+    Sandbox,
 }
 
 pub fn app() -> App {
@@ -23,11 +46,23 @@ pub fn app() -> App {
         ..Default::default()
     })
     .add_plugins(DefaultPlugins)
+    .add_plugin(level_pack::LevelPackPlugin)
+    .add_plugin(input::InputPlugin)
+    .add_plugin(settings::SettingsPlugin)
     .add_plugin(main_menu::MainMenuPlugin)
+    .add_plugin(settings_menu::SettingsMenuPlugin)
+    .add_plugin(stats_menu::StatsMenuPlugin)
+    .add_plugin(recipe_finder::RecipeFinderPlugin)
     .add_plugin(game::GamePlugin)
+    .add_plugin(sandbox::SandboxPlugin)
+    .add_plugin(pause::PausePlugin)
+    .add_plugin(save::SavePlugin)
+    .add_plugin(stats::StatsPlugin)
+    .add_plugin(daily::DailyPlugin)
     .add_plugin(game_ui::GameUiPlugin)
     .add_plugin(widgets::GameButtonPlugin)
     .add_plugin(widgets::GameIndicatorPlugin)
+    .add_plugin(widgets::FocusPlugin)
     .add_state(AppState::MainMenu)
     .add_startup_system(setup);
     app
@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 
 use crate::{
-    game::GameOptions,
+    game::GameMode,
+    level_pack::{ActiveLevelPack, LevelPack},
+    save::{ResumeRun, SavedRun},
+    settings::Settings,
     widgets::{spawn_game_button, GameButton},
     AppState,
 };
@@ -16,11 +19,26 @@ impl Plugin for MainMenuPlugin {
         .add_system_set(
             SystemSet::on_exit(AppState::MainMenu).with_system(teardown),
         )
+        // NOTE: the settings, statistics and recipe finder are pushed over the
+        // menu, which hides meanwhile
+        .add_system_set(
+            SystemSet::on_pause(AppState::MainMenu).with_system(teardown),
+        )
+        .add_system_set(
+            SystemSet::on_resume(AppState::MainMenu).with_system(setup),
+        )
         .add_system_set(
             SystemSet::on_update(AppState::MainMenu)
-                .with_system(play)
+                .with_system(continue_run)
+                .with_system(handle_play_clicked)
+                .with_system(play_sandbox)
                 .with_system(toggle_mute)
-                .with_system(update_mute_button),
+                .with_system(update_mute_button)
+                .with_system(cycle_difficulty)
+                .with_system(update_difficulty_button)
+                .with_system(open_settings)
+                .with_system(open_stats)
+                .with_system(open_recipe_finder),
         );
     }
 }
@@ -29,23 +47,72 @@ impl Plugin for MainMenuPlugin {
 struct MainMenu;
 
 #[derive(Component)]
-struct MenuItem;
+struct ContinueButton;
 
+/// Starts a run in the given mode.
 #[derive(Component)]
-struct PlayButton;
+struct PlayButton(GameMode);
+
+#[derive(Component)]
+struct SandboxButton;
 
 #[derive(Component)]
 struct MuteButton;
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let play_button = spawn_game_button(
+#[derive(Component)]
+struct DifficultyButton;
+
+#[derive(Component)]
+struct SettingsButton;
+
+#[derive(Component)]
+struct StatsButton;
+
+#[derive(Component)]
+struct RecipeFinderButton;
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    saved_run: Res<SavedRun>,
+) {
+    let continue_button = saved_run.0.is_some().then(|| {
+        let continue_button = spawn_game_button(
+            &mut commands,
+            &asset_server,
+            GameButton {
+                text: "Continue".into(),
+            },
+        );
+        commands.entity(continue_button).insert(ContinueButton);
+        continue_button
+    });
+
+    let play_buttons = [
+        ("Play", GameMode::Campaign),
+        ("Timed", GameMode::Timed),
+        ("Reverse", GameMode::Reverse),
+        ("Endless", GameMode::Endless),
+        ("Daily", GameMode::Daily),
+    ]
+    .map(|(text, mode)| {
+        let play_button = spawn_game_button(
+            &mut commands,
+            &asset_server,
+            GameButton { text: text.into() },
+        );
+        commands.entity(play_button).insert(PlayButton(mode));
+        play_button
+    });
+
+    let sandbox_button = spawn_game_button(
         &mut commands,
         &asset_server,
         GameButton {
-            text: "Play".into(),
+            text: "Sandbox".into(),
         },
     );
-    commands.entity(play_button).insert(PlayButton);
+    commands.entity(sandbox_button).insert(SandboxButton);
 
     let mute_button = spawn_game_button(
         &mut commands,
@@ -56,6 +123,44 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     );
     commands.entity(mute_button).insert(MuteButton);
 
+    let difficulty_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Difficulty".into(),
+        },
+    );
+    commands.entity(difficulty_button).insert(DifficultyButton);
+
+    let settings_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Settings".into(),
+        },
+    );
+    commands.entity(settings_button).insert(SettingsButton);
+
+    let stats_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Statistics".into(),
+        },
+    );
+    commands.entity(stats_button).insert(StatsButton);
+
+    let recipe_finder_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Recipe finder".into(),
+        },
+    );
+    commands
+        .entity(recipe_finder_button)
+        .insert(RecipeFinderButton);
+
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
@@ -107,8 +212,8 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                     );
                 });
 
-            main_container
-                .spawn_bundle(NodeBundle {
+            let mut buttons_container =
+                main_container.spawn_bundle(NodeBundle {
                     color: Color::NONE.into(),
                     style: Style {
                         margin: UiRect {
@@ -123,9 +228,20 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                         ..default()
                     },
                     ..default()
-                })
-                .add_child(play_button)
-                .add_child(mute_button);
+                });
+            if let Some(continue_button) = continue_button {
+                buttons_container.add_child(continue_button);
+            }
+            for play_button in play_buttons {
+                buttons_container.add_child(play_button);
+            }
+            buttons_container
+                .add_child(sandbox_button)
+                .add_child(difficulty_button)
+                .add_child(mute_button)
+                .add_child(settings_button)
+                .add_child(stats_button)
+                .add_child(recipe_finder_button);
         });
 }
 
@@ -135,46 +251,172 @@ fn teardown(mut commands: Commands, query: Query<Entity, With<MainMenu>>) {
     }
 }
 
-fn play(
+fn continue_run(
+    mut commands: Commands,
     mut app_state: ResMut<State<AppState>>,
-    query: Query<&Interaction, (With<PlayButton>, Changed<Interaction>)>,
+    mut mode: ResMut<GameMode>,
+    saved_run: Res<SavedRun>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+    query: Query<&Interaction, (With<ContinueButton>, Changed<Interaction>)>,
 ) {
-    let clicked = query.iter().any(|interaction| match interaction {
-        Interaction::Clicked => true,
-        _ => false,
-    });
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
 
-    if clicked {
+    let (run, pack) = match (&saved_run.0, packs.get(&active_pack.0)) {
+        (Some(run), Some(pack)) => (run, pack),
+        _ => return,
+    };
+
+    // NOTE: the pack may have lost levels since the run was saved
+    let level_exists = run
+        .mode
+        .level_count(pack)
+        .is_none_or(|count| run.level_index < count);
+    if clicked && level_exists {
+        *mode = run.mode;
+        commands.insert_resource(ResumeRun(run.clone()));
         app_state.set(AppState::InGame).unwrap();
     }
 }
 
+fn handle_play_clicked(
+    mut app_state: ResMut<State<AppState>>,
+    mut mode: ResMut<GameMode>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+    query: Query<(&Interaction, &PlayButton), Changed<Interaction>>,
+) {
+    let clicked =
+        query
+            .iter()
+            .find_map(|(interaction, button)| match interaction {
+                Interaction::Clicked => Some(button.0),
+                _ => None,
+            });
+
+    // NOTE: levels are read from the pack as soon as the game starts, and
+    // generated levels use its palette, so wait until it is done loading.
+    if let Some(clicked) = clicked {
+        if packs.contains(&active_pack.0) {
+            *mode = clicked;
+            app_state.set(AppState::InGame).unwrap();
+        }
+    }
+}
+
+fn play_sandbox(
+    mut app_state: ResMut<State<AppState>>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+    query: Query<&Interaction, (With<SandboxButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    // NOTE: the sandbox mixes the palette and pigments of the active pack.
+    if clicked && packs.contains(&active_pack.0) {
+        app_state.set(AppState::Sandbox).unwrap();
+    }
+}
+
 fn toggle_mute(
-    mut options: ResMut<GameOptions>,
+    mut settings: ResMut<Settings>,
     query: Query<&Interaction, (With<MuteButton>, Changed<Interaction>)>,
 ) {
-    let clicked = query.iter().any(|interaction| match interaction {
-        Interaction::Clicked => true,
-        _ => false,
-    });
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
 
     if clicked {
-        options.mute = !options.mute;
+        settings.mute = !settings.mute;
     }
 }
 
 fn update_mute_button(
-    options: Res<GameOptions>,
+    settings: Res<Settings>,
     mut query: Query<&mut GameButton, With<MuteButton>>,
 ) {
     query.iter_mut().for_each(|mut btn| {
-        let text = match options.mute {
+        let text = match settings.mute {
             true => "Unmute sound",
             false => "Mute sound",
         };
-        
+
         if btn.text != text {
             btn.text = text.into();
         }
     })
 }
+
+fn cycle_difficulty(
+    mut settings: ResMut<Settings>,
+    query: Query<&Interaction, (With<DifficultyButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        settings.difficulty = settings.difficulty.next();
+    }
+}
+
+fn update_difficulty_button(
+    settings: Res<Settings>,
+    mut query: Query<&mut GameButton, With<DifficultyButton>>,
+) {
+    for mut button in query.iter_mut() {
+        let text = format!("Difficulty: {}", settings.difficulty.name());
+        if button.text != text {
+            button.text = text;
+        }
+    }
+}
+
+fn open_settings(
+    mut app_state: ResMut<State<AppState>>,
+    query: Query<&Interaction, (With<SettingsButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        app_state.push(AppState::Settings).unwrap();
+    }
+}
+
+fn open_stats(
+    mut app_state: ResMut<State<AppState>>,
+    query: Query<&Interaction, (With<StatsButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        app_state.push(AppState::Stats).unwrap();
+    }
+}
+
+fn open_recipe_finder(
+    mut app_state: ResMut<State<AppState>>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+    query: Query<
+        &Interaction,
+        (With<RecipeFinderButton>, Changed<Interaction>),
+    >,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    // NOTE: recipes are made from the palette of the active pack.
+    if clicked && packs.contains(&active_pack.0) {
+        app_state.push(AppState::RecipeFinder).unwrap();
+    }
+}
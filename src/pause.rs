@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::{LevelState, ResetLevelEvent},
+    input::{Action, ActionState},
+    widgets::{spawn_game_button, GameButton},
+    AppState,
+};
+
+/// Overlay pushed over the game, which stops it until the player resumes.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<QuitToMenuEvent>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Paused).with_system(setup),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Paused).with_system(teardown),
+            )
+            // NOTE: the settings can be pushed over the overlay, which hides
+            // meanwhile
+            .add_system_set(
+                SystemSet::on_pause(AppState::Paused).with_system(teardown),
+            )
+            .add_system_set(
+                SystemSet::on_resume(AppState::Paused).with_system(setup),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Paused)
+                    .with_system(resume)
+                    .with_system(restart_level)
+                    .with_system(open_settings)
+                    .with_system(quit),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(quit_to_menu),
+            );
+    }
+}
+
+/// Sent when leaving the overlay to quit the game, which can only be done
+/// once the game is resumed.
+struct QuitToMenuEvent;
+
+#[derive(Component)]
+struct PauseOverlay;
+
+#[derive(Component)]
+struct ResumeButton;
+
+#[derive(Component)]
+struct RestartButton;
+
+#[derive(Component)]
+struct SettingsButton;
+
+#[derive(Component)]
+struct QuitButton;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let resume_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Resume".into(),
+        },
+    );
+    commands.entity(resume_button).insert(ResumeButton);
+
+    let restart_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Restart level".into(),
+        },
+    );
+    commands.entity(restart_button).insert(RestartButton);
+
+    let settings_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Settings".into(),
+        },
+    );
+    commands.entity(settings_button).insert(SettingsButton);
+
+    let quit_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Quit to menu".into(),
+        },
+    );
+    commands.entity(quit_button).insert(QuitButton);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(PauseOverlay)
+        .with_children(|main_container| {
+            main_container.spawn_bundle(TextBundle::from_section(
+                "Paused",
+                TextStyle {
+                    font: asset_server.load("edosz.ttf"),
+                    font_size: 45.0,
+                    color: Color::GREEN,
+                },
+            ));
+        })
+        .add_child(resume_button)
+        .add_child(restart_button)
+        .add_child(settings_button)
+        .add_child(quit_button);
+}
+
+fn teardown(mut commands: Commands, query: Query<Entity, With<PauseOverlay>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn resume(
+    mut app_state: ResMut<State<AppState>>,
+    mut actions: ResMut<ActionState>,
+    query: Query<&Interaction, (With<ResumeButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+    let pressed = actions.just_pressed(Action::Menu)
+        || actions.just_pressed(Action::Pause);
+
+    if clicked || pressed {
+        actions.consume(Action::Menu);
+        actions.consume(Action::Pause);
+        app_state.pop().unwrap();
+    }
+}
+
+fn restart_level(
+    mut app_state: ResMut<State<AppState>>,
+    level: Option<Res<LevelState>>,
+    mut reset_evw: EventWriter<ResetLevelEvent>,
+    query: Query<&Interaction, (With<RestartButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if !clicked {
+        return;
+    }
+
+    // NOTE: a locked level is reset once its outcome is no longer shown
+    if level.is_some_and(|level| !level.locked) {
+        reset_evw.send(ResetLevelEvent);
+    }
+    app_state.pop().unwrap();
+}
+
+fn open_settings(
+    mut app_state: ResMut<State<AppState>>,
+    query: Query<&Interaction, (With<SettingsButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        app_state.push(AppState::Settings).unwrap();
+    }
+}
+
+fn quit(
+    mut app_state: ResMut<State<AppState>>,
+    mut quit_evw: EventWriter<QuitToMenuEvent>,
+    query: Query<&Interaction, (With<QuitButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        quit_evw.send(QuitToMenuEvent);
+        app_state.pop().unwrap();
+    }
+}
+
+fn quit_to_menu(
+    mut app_state: ResMut<State<AppState>>,
+    mut quit_evr: EventReader<QuitToMenuEvent>,
+) {
+    if quit_evr.iter().count() > 0 {
+        app_state.set(AppState::MainMenu).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+use crate::{color_mixer::MixingModel, level_pack::PaletteColor};
+
+/// A mix of palette colors, stored as how many times each palette index is
+/// used.
+///
+/// Mixing weighs colors by their number of parts, so the order in which colors
+/// are added doesn't matter and recipes are compared as multisets. Two recipes
+/// that use the same colors in the same proportions, like `2R + 2Y` and
+/// `R + Y`, are [`equivalent`](Recipe::equivalent) and mix into the same color.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Recipe {
+    // NOTE: trailing zeros are always trimmed so that equal multisets have
+    // the same representation, which derived equality and hashing rely on.
+    counts: Vec<u32>,
+}
+
+impl Recipe {
+    pub fn new() -> Self {
+        default()
+    }
+
+    pub fn from_indices(indices: impl IntoIterator<Item = usize>) -> Self {
+        let mut recipe = Recipe::new();
+        for index in indices {
+            recipe.add(index);
+        }
+        recipe
+    }
+
+    pub fn add(&mut self, index: usize) {
+        self.add_parts(index, 1);
+    }
+
+    /// Adds `parts` of the color at `index` at once, like a bigger brush would.
+    pub fn add_parts(&mut self, index: usize, parts: u32) {
+        if parts == 0 {
+            return;
+        }
+        if self.counts.len() <= index {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += parts;
+    }
+
+    /// Removes up to `parts` of the color at `index`.
+    pub fn remove_parts(&mut self, index: usize, parts: u32) {
+        if let Some(count) = self.counts.get_mut(index) {
+            *count = count.saturating_sub(parts);
+        }
+        while self.counts.last() == Some(&0) {
+            self.counts.pop();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    /// Total number of parts in the recipe. With single-part brushes, this is
+    /// its complexity.
+    pub fn len(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Palette indices used by the recipe and how many times each is used.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(index, count)| (index, *count))
+    }
+
+    /// The smallest recipe that mixes into the same color, found by dividing
+    /// every count by their greatest common divisor.
+    pub fn reduced(&self) -> Recipe {
+        let divisor = self.counts.iter().fold(0, |acc, count| gcd(acc, *count));
+        if divisor <= 1 {
+            return self.clone();
+        }
+
+        Recipe {
+            counts: self.counts.iter().map(|count| count / divisor).collect(),
+        }
+    }
+
+    /// Whether a smaller recipe mixes into the same color.
+    pub fn is_reducible(&self) -> bool {
+        self.reduced().len() < self.len()
+    }
+
+    /// Whether both recipes use the same colors in the same proportions.
+    pub fn equivalent(&self, other: &Recipe) -> bool {
+        self.reduced() == other.reduced()
+    }
+
+    /// Whether `other` has at least as many parts of every color as this
+    /// recipe, so that adding to this recipe can still make it.
+    pub fn is_part_of(&self, other: &Recipe) -> bool {
+        self.iter().all(|(index, count)| {
+            other.counts.get(index).is_some_and(|other| count <= *other)
+        })
+    }
+
+    /// Whether every index of the recipe exists in a palette of that size.
+    pub fn fits_palette(&self, palette_len: usize) -> bool {
+        self.counts.len() <= palette_len
+    }
+
+    /// Fewest brush clicks needed to make this recipe when each click adds
+    /// one of the given amounts of parts, or `None` if it can't be made.
+    pub fn min_clicks(&self, amounts: &[u32]) -> Option<u32> {
+        self.iter()
+            .map(|(_, count)| min_clicks_for(count, amounts))
+            .sum()
+    }
+
+    /// Lists the recipe's colors, like "2 Red + Yellow".
+    pub fn describe(&self, palette: &[PaletteColor]) -> String {
+        self.iter()
+            .map(|(index, count)| match count {
+                1 => palette[index].name.clone(),
+                _ => format!("{} {}", count, palette[index].name),
+            })
+            .collect::<Vec<String>>()
+            .join(" + ")
+    }
+
+    pub fn mix(
+        &self,
+        palette: &[PaletteColor],
+        model: &dyn MixingModel,
+    ) -> Color {
+        let weighted: Vec<(Color, f32)> = self
+            .iter()
+            .map(|(index, count)| (palette[index].color, count as f32))
+            .collect();
+        model.mix_weighted(&weighted)
+    }
+}
+
+impl FromIterator<usize> for Recipe {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        Recipe::from_indices(iter)
+    }
+}
+
+// coin change: fewest amounts summing up to exactly `count`
+fn min_clicks_for(count: u32, amounts: &[u32]) -> Option<u32> {
+    let count = count as usize;
+    let mut clicks: Vec<Option<u32>> = vec![None; count + 1];
+    clicks[0] = Some(0);
+
+    for total in 1..=count {
+        clicks[total] = amounts
+            .iter()
+            .filter(|amount| **amount > 0 && **amount as usize <= total)
+            .filter_map(|amount| clicks[total - *amount as usize])
+            .min()
+            .map(|min| min + 1);
+    }
+
+    clicks[count]
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const RED: usize = 0;
+    const YELLOW: usize = 1;
+    const BLUE: usize = 2;
+
+    #[test]
+    fn proportional_recipes_reduce_to_the_same_recipe() {
+        let mut doubled = Recipe::new();
+        doubled.add_parts(RED, 2);
+        doubled.add_parts(YELLOW, 2);
+        let single = Recipe::from_indices([YELLOW, RED]);
+
+        assert_eq!(doubled.reduced(), single);
+        assert!(doubled.is_reducible());
+        assert!(!single.is_reducible());
+        assert!(doubled.equivalent(&single));
+        assert!(!doubled.equivalent(&Recipe::from_indices([RED, RED, YELLOW])));
+    }
+
+    #[test]
+    fn removing_parts_keeps_recipes_comparable() {
+        let mut recipe = Recipe::from_indices([RED, BLUE, BLUE]);
+        recipe.remove_parts(BLUE, 2);
+        assert_eq!(recipe, Recipe::from_indices([RED]));
+
+        assert_eq!(recipe.counts, vec![1]);
+        let recipes = HashSet::from([Recipe::from_indices([RED])]);
+        assert!(recipes.contains(&recipe));
+
+        recipe.remove_parts(RED, 5);
+        assert!(recipe.is_empty());
+        assert_eq!(recipe, Recipe::new());
+    }
+
+    #[test]
+    fn min_clicks_combines_brush_amounts() {
+        let recipe = |red, yellow| {
+            let mut recipe = Recipe::new();
+            recipe.add_parts(RED, red);
+            recipe.add_parts(YELLOW, yellow);
+            recipe
+        };
+
+        assert_eq!(recipe(3, 1).min_clicks(&[1, 3]), Some(2));
+        assert_eq!(recipe(4, 2).min_clicks(&[1, 3]), Some(4));
+        assert_eq!(recipe(7, 0).min_clicks(&[2, 5]), Some(2));
+        assert_eq!(recipe(6, 0).min_clicks(&[2, 5]), Some(3));
+        // NOTE: no sum of twos and fives makes three parts
+        assert_eq!(recipe(3, 0).min_clicks(&[2, 5]), None);
+        assert_eq!(recipe(5, 3).min_clicks(&[2, 5]), None);
+        assert_eq!(Recipe::new().min_clicks(&[2, 5]), Some(0));
+    }
+}
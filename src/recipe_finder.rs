@@ -0,0 +1,324 @@
+use bevy::prelude::*;
+
+use crate::{
+    color_distance::ColorMetric,
+    color_mixer::MixingModelKind,
+    color_vision::simulate_color,
+    input::{Action, ActionState},
+    level_generator::MAX_COMPLEXITY,
+    level_pack::{ActiveLevelPack, LevelPack},
+    settings::Settings,
+    solver::Solver,
+    widgets::{spawn_game_button, GameButton},
+    AppState,
+};
+
+/// Number of recipes shown for the typed color.
+const SHOWN_RECIPES: usize = 5;
+
+/// Number of hex digits in a color.
+const HEX_DIGITS: usize = 6;
+
+/// Screen where players type any color and see how to paint it with the
+/// active pack's palette. Pushed over the main menu.
+pub struct RecipeFinderPlugin;
+
+impl Plugin for RecipeFinderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::RecipeFinder).with_system(setup),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::RecipeFinder).with_system(teardown),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::RecipeFinder)
+                .with_system(type_color)
+                .with_system(update_results.after(type_color))
+                .with_system(back),
+        );
+    }
+}
+
+/// Hex digits typed so far.
+#[derive(Default)]
+struct TypedColor(String);
+
+#[derive(Component)]
+struct RecipeFinderMenu;
+
+#[derive(Component)]
+struct TypedColorText;
+
+#[derive(Component)]
+struct TargetSwatch;
+
+/// One of the listed recipes, by rank.
+#[derive(Component)]
+struct ResultRow(usize);
+
+#[derive(Component)]
+struct ResultSwatch(usize);
+
+#[derive(Component)]
+struct ResultText(usize);
+
+#[derive(Component)]
+struct BackButton;
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mixing: Res<MixingModelKind>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+) {
+    // NOTE: the menu only opens this screen once the pack is loaded
+    let palette = packs
+        .get(&active_pack.0)
+        .map_or_else(Vec::new, |pack| pack.palette.clone());
+    commands.insert_resource(Solver::new(palette, *mixing));
+    commands.insert_resource(TypedColor::default());
+
+    let back_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Back".into(),
+        },
+    );
+    commands.entity(back_button).insert(BackButton);
+
+    let text_style = TextStyle {
+        font: asset_server.load("edosz.ttf"),
+        font_size: 20.0,
+        color: Color::YELLOW_GREEN,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(RecipeFinderMenu)
+        .with_children(|main_container| {
+            main_container.spawn_bundle(TextBundle::from_section(
+                "Recipe finder",
+                TextStyle {
+                    font: asset_server.load("edosz.ttf"),
+                    font_size: 45.0,
+                    color: Color::GREEN,
+                },
+            ));
+            main_container.spawn_bundle(TextBundle::from_section(
+                "Type a hex color to see how to paint it",
+                text_style.clone(),
+            ));
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|input_row| {
+                    input_row
+                        .spawn_bundle(TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 30.0,
+                                ..text_style.clone()
+                            },
+                        ))
+                        .insert(TypedColorText);
+                    input_row
+                        .spawn_bundle(NodeBundle {
+                            color: Color::NONE.into(),
+                            style: Style {
+                                size: Size::new(Val::Px(40.0), Val::Px(40.0)),
+                                margin: UiRect::all(Val::Px(10.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .insert(TargetSwatch);
+                });
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|results| {
+                    for rank in 0..SHOWN_RECIPES {
+                        results
+                            .spawn_bundle(NodeBundle {
+                                color: Color::NONE.into(),
+                                style: Style {
+                                    display: Display::None,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .insert(ResultRow(rank))
+                            .with_children(|row| {
+                                row.spawn_bundle(NodeBundle {
+                                    style: Style {
+                                        size: Size::new(
+                                            Val::Px(24.0),
+                                            Val::Px(24.0),
+                                        ),
+                                        margin: UiRect::all(Val::Px(5.0)),
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .insert(ResultSwatch(rank));
+                                row.spawn_bundle(TextBundle::from_section(
+                                    "",
+                                    text_style.clone(),
+                                ))
+                                .insert(ResultText(rank));
+                            });
+                    }
+                });
+        })
+        .add_child(back_button);
+}
+
+fn teardown(
+    mut commands: Commands,
+    query: Query<Entity, With<RecipeFinderMenu>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Solver>();
+    commands.remove_resource::<TypedColor>();
+}
+
+fn type_color(
+    typed: Option<ResMut<TypedColor>>,
+    keys: Res<Input<KeyCode>>,
+    mut char_evr: EventReader<ReceivedCharacter>,
+) {
+    let mut typed = match typed {
+        Some(typed) => typed,
+        None => return,
+    };
+
+    for event in char_evr.iter() {
+        if event.char.is_ascii_hexdigit() && typed.0.len() < HEX_DIGITS {
+            typed.0.push(event.char.to_ascii_uppercase());
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        typed.0.pop();
+    }
+}
+
+// NOTE: the typed color and every result row are updated together
+#[allow(clippy::too_many_arguments)]
+fn update_results(
+    settings: Res<Settings>,
+    typed: Option<Res<TypedColor>>,
+    solver: Option<ResMut<Solver>>,
+    mut text_query: Query<
+        &mut Text,
+        (With<TypedColorText>, Without<ResultText>),
+    >,
+    mut swatch_query: Query<
+        &mut UiColor,
+        (With<TargetSwatch>, Without<ResultSwatch>),
+    >,
+    mut row_query: Query<(&ResultRow, &mut Style)>,
+    mut result_swatch_query: Query<(&ResultSwatch, &mut UiColor)>,
+    mut result_text_query: Query<(&ResultText, &mut Text)>,
+) {
+    let (typed, mut solver) = match (typed, solver) {
+        (Some(typed), Some(solver)) => (typed, solver),
+        _ => return,
+    };
+    if !typed.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let target = match typed.0.len() {
+        HEX_DIGITS => Color::hex(&typed.0).ok(),
+        _ => None,
+    };
+    let solutions = match target {
+        Some(target) => solver.ranked(
+            target,
+            MAX_COMPLEXITY,
+            ColorMetric::Ciede2000,
+            SHOWN_RECIPES,
+        ),
+        None => Vec::new(),
+    };
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("#{:_<6}", typed.0);
+    }
+    for mut color in swatch_query.iter_mut() {
+        *color = target
+            .map_or(Color::NONE, |target| {
+                simulate_color(target, settings.color_vision)
+            })
+            .into();
+    }
+
+    for (row, mut style) in row_query.iter_mut() {
+        style.display = match row.0 < solutions.len() {
+            true => Display::Flex,
+            false => Display::None,
+        };
+    }
+    for (swatch, mut color) in result_swatch_query.iter_mut() {
+        if let Some(solution) = solutions.get(swatch.0) {
+            *color =
+                simulate_color(solution.color, settings.color_vision).into();
+        }
+    }
+    for (result, mut text) in result_text_query.iter_mut() {
+        if let Some(solution) = solutions.get(result.0) {
+            text.sections[0].value = format!(
+                "{} (off by {:.1})",
+                solution.recipe.describe(solver.palette()),
+                solution.distance
+            );
+        }
+    }
+}
+
+fn back(
+    mut app_state: ResMut<State<AppState>>,
+    mut actions: ResMut<ActionState>,
+    query: Query<&Interaction, (With<BackButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked || actions.just_pressed(Action::Menu) {
+        actions.consume(Action::Menu);
+        app_state.pop().unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use crate::color_distance::{oklab_to_srgb, srgb_to_oklab, ColorMetric};
+
+/// Fewest and most swatches offered by a reverse puzzle.
+pub const MIN_CANDIDATES: usize = 4;
+pub const MAX_CANDIDATES: usize = 6;
+
+/// Oklab distance of the farthest distractor.
+const FARTHEST_DISTRACTOR: f32 = 0.2;
+
+/// Oklab distance of the closest distractor in the easiest puzzles.
+const EASIEST_DISTRACTOR: f32 = 0.12;
+
+/// Oklab distance of the closest distractor in the hardest puzzles, still a
+/// couple of times what players can tell apart side by side.
+const HARDEST_DISTRACTOR: f32 = 0.04;
+
+/// How much closer the closest distractor gets with every click of
+/// complexity.
+const DISTRACTOR_STEP: f32 = 0.02;
+
+const MAX_ATTEMPTS: usize = 50;
+
+/// Steps along each channel of the grid that distractors fall back to.
+const GRID_STEPS: u32 = 4;
+
+/// A level played backwards: the recipe is shown and the player picks the
+/// mixed color among a few swatches.
+#[derive(Clone, Debug)]
+pub struct ReversePuzzle {
+    /// Swatches offered to the player, one of which is the mixed color.
+    pub candidates: Vec<Color>,
+    /// Index of the mixed color in `candidates`.
+    pub answer: usize,
+    /// Index of the swatch picked by the player, if any.
+    pub picked: Option<usize>,
+}
+
+impl ReversePuzzle {
+    /// Builds a puzzle for `target`, whose distractors get closer to it as
+    /// `complexity` rises.
+    ///
+    /// Puzzles are generated from `seed`, so the same seed always offers the
+    /// same swatches in the same order.
+    pub fn generate(target: Color, complexity: u32, seed: u64) -> Self {
+        let mut rng = Pcg32::seed_from_u64(seed);
+        let count =
+            (complexity as usize + 2).clamp(MIN_CANDIDATES, MAX_CANDIDATES);
+        let closest = (EASIEST_DISTRACTOR
+            - DISTRACTOR_STEP * complexity.saturating_sub(2) as f32)
+            .max(HARDEST_DISTRACTOR);
+
+        let mut candidates = vec![target];
+        for distance in distractor_distances(count - 1, closest) {
+            let color = distractor(&mut rng, target, distance, &candidates);
+            candidates.push(color);
+        }
+
+        // NOTE: the mixed color was added first, move it to a random spot
+        let answer = rng.gen_range(0..count);
+        candidates.swap(0, answer);
+
+        Self {
+            candidates,
+            answer,
+            picked: None,
+        }
+    }
+
+    pub fn picked_color(&self) -> Option<Color> {
+        self.picked
+            .and_then(|index| self.candidates.get(index).copied())
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.picked == Some(self.answer)
+    }
+}
+
+/// Distances of `count` distractors, spread evenly from `closest` to the
+/// farthest.
+fn distractor_distances(count: usize, closest: f32) -> Vec<f32> {
+    (0..count)
+        .map(|index| match count {
+            1 => closest,
+            _ => {
+                let t = index as f32 / (count - 1) as f32;
+                closest + t * (FARTHEST_DISTRACTOR - closest)
+            }
+        })
+        .collect()
+}
+
+/// Finds a displayable color at `distance` from `target` in Oklab, that
+/// can't be mistaken for any of the colors already `taken`.
+fn distractor(
+    rng: &mut Pcg32,
+    target: Color,
+    distance: f32,
+    taken: &[Color],
+) -> Color {
+    let [r, g, b, _] = target.as_rgba_f32();
+    let center = srgb_to_oklab([r, g, b].map(f64::from));
+    let min_separation = 0.5 * distance.min(HARDEST_DISTRACTOR * 2.0);
+
+    for _ in 0..MAX_ATTEMPTS {
+        // NOTE: mostly shift hue and chroma, lightness differences alone are
+        // too easy to spot
+        let hue = rng.gen_range(0.0..std::f32::consts::TAU);
+        let tilt = rng.gen_range(-0.5..0.5f32);
+        let offset =
+            [tilt.sin(), tilt.cos() * hue.cos(), tilt.cos() * hue.sin()]
+                .map(|component| f64::from(component * distance));
+
+        let srgb = oklab_to_srgb([
+            center[0] + offset[0],
+            center[1] + offset[1],
+            center[2] + offset[2],
+        ]);
+        if !srgb.iter().all(|channel| (0.0..=1.0).contains(channel)) {
+            continue;
+        }
+
+        let [r, g, b] = srgb.map(|channel| channel as f32);
+        let color = Color::rgb(r, g, b);
+        if separation(color, taken) >= min_separation {
+            return color;
+        }
+    }
+
+    // NOTE: colors near the edges of the gamut have few neighbours at the
+    // asked distance, so the closest match is picked from a coarse grid of
+    // displayable colors instead. Its corners are far enough apart that the
+    // few colors taken can't crowd all of them out.
+    let steps: Vec<f32> = (0..=GRID_STEPS)
+        .map(|step| step as f32 / GRID_STEPS as f32)
+        .collect();
+    let mut grid = Vec::new();
+    for r in &steps {
+        for g in &steps {
+            for b in &steps {
+                grid.push(Color::rgb(*r, *g, *b));
+            }
+        }
+    }
+
+    grid.into_iter()
+        .filter(|color| separation(*color, taken) >= min_separation)
+        .min_by(|a, b| {
+            let offset = |color: &Color| {
+                (ColorMetric::Oklab.distance(*color, target) - distance).abs()
+            };
+            offset(a).total_cmp(&offset(b))
+        })
+        .expect("the corners of the gamut can't all be taken")
+}
+
+/// Oklab distance from `color` to the closest of the `taken` colors.
+fn separation(color: Color, taken: &[Color]) -> f32 {
+    taken
+        .iter()
+        .map(|other| ColorMetric::Oklab.distance(color, *other))
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: u64 = 20;
+
+    fn assert_separated(target: Color) {
+        for complexity in 1..=8 {
+            for seed in 0..SEEDS {
+                let puzzle = ReversePuzzle::generate(target, complexity, seed);
+                assert_eq!(puzzle.candidates[puzzle.answer], target);
+
+                let candidates = &puzzle.candidates;
+                for (index, color) in candidates.iter().enumerate() {
+                    let distance = separation(*color, &candidates[..index]);
+                    assert!(
+                        distance >= HARDEST_DISTRACTOR / 2.0,
+                        "{:?} has swatches {} apart",
+                        target,
+                        distance
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn distractors_are_separated() {
+        for target in [Color::ORANGE, Color::rgb(0.4, 0.3, 0.6), Color::GRAY] {
+            assert_separated(target);
+        }
+    }
+
+    #[test]
+    fn distractors_are_separated_near_the_gamut_edges() {
+        let targets = [
+            Color::WHITE,
+            Color::rgb(0.98, 0.98, 0.97),
+            Color::BLACK,
+            Color::rgb(0.02, 0.03, 0.02),
+            Color::RED,
+            Color::YELLOW,
+            Color::BLUE,
+        ];
+        for target in targets {
+            assert_separated(target);
+        }
+    }
+
+    #[test]
+    fn same_seed_offers_the_same_swatches() {
+        let target = Color::rgb(0.2, 0.5, 0.3);
+        let a = ReversePuzzle::generate(target, 4, 7);
+        let b = ReversePuzzle::generate(target, 4, 7);
+        assert_eq!(a.candidates, b.candidates);
+        assert_eq!(a.answer, b.answer);
+    }
+}
@@ -0,0 +1,578 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color_mixer::MixingModelKind,
+    color_vision::simulate_color,
+    game::ColorSelector,
+    game_ui::{
+        contrasting_text, describe_color, spawn_brush, spawn_color_readout,
+        ColorReadout, PlayerColor,
+    },
+    input::{Action, ActionState},
+    level_pack::{ActiveLevelPack, LevelPack, PaletteColor},
+    recipe::Recipe,
+    settings::{play_effect, Settings},
+    storage::StorageBackend,
+    widgets::{spawn_game_button, GameButton},
+    AppState,
+};
+
+const SWATCHES_KEY: &str = "swatches";
+
+/// Number of swatches kept in the personal palette. Saving more drops the
+/// oldest.
+pub const MAX_SWATCHES: usize = 12;
+
+/// Free play, where colors are mixed from every color of the active pack
+/// without an objective or lives.
+pub struct SandboxPlugin;
+
+impl Plugin for SandboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersonalPalette>()
+            .add_startup_system(load_personal_palette)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Sandbox).with_system(setup),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Sandbox).with_system(teardown),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Sandbox)
+                    .with_system(handle_color_clicked)
+                    .with_system(handle_color_keys)
+                    .with_system(handle_undo)
+                    .with_system(handle_clear)
+                    .with_system(save_swatch)
+                    .with_system(load_swatch)
+                    .with_system(update_mix)
+                    .with_system(rebuild_swatches)
+                    .with_system(back_to_menu),
+            );
+    }
+}
+
+/// A mix saved by the player, with the colors it was made of.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Swatch {
+    pub color: Color,
+    /// Parts of each color, by name, so that the recipe outlives palette
+    /// changes.
+    pub recipe: Vec<(String, u32)>,
+}
+
+/// Swatches saved from the sandbox, oldest first, saved to storage.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PersonalPalette {
+    pub swatches: Vec<Swatch>,
+}
+
+impl PersonalPalette {
+    /// Adds `swatch` unless it is already saved. Returns whether it was added.
+    pub fn add(&mut self, swatch: Swatch) -> bool {
+        if self.swatches.contains(&swatch) {
+            return false;
+        }
+
+        self.swatches.push(swatch);
+        if self.swatches.len() > MAX_SWATCHES {
+            self.swatches.remove(0);
+        }
+        true
+    }
+}
+
+/// The mix being made in the sandbox.
+pub struct SandboxState {
+    pub palette: Vec<PaletteColor>,
+    pub mixing: MixingModelKind,
+    pub selected: Recipe,
+    /// Palette indices in the order they were added, for undo.
+    pub history: Vec<usize>,
+}
+
+impl SandboxState {
+    pub fn select(&mut self, index: usize) {
+        self.selected.add(index);
+        self.history.push(index);
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(index) => {
+                self.selected.remove_parts(index, 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.history.clear();
+    }
+
+    pub fn color(&self) -> Color {
+        self.selected.mix(&self.palette, self.mixing.model())
+    }
+
+    pub fn swatch(&self) -> Swatch {
+        Swatch {
+            color: self.color(),
+            recipe: self
+                .selected
+                .iter()
+                .map(|(index, count)| (self.palette[index].name.clone(), count))
+                .collect(),
+        }
+    }
+
+    /// Replaces the mix with the recipe of `swatch`. Colors that are no
+    /// longer in the palette are left out.
+    pub fn restore(&mut self, swatch: &Swatch) {
+        self.clear();
+        for (name, count) in swatch.recipe.iter() {
+            let index = self.palette.iter().position(|c| &c.name == name);
+            if let Some(index) = index {
+                for _ in 0..*count {
+                    self.select(index);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct SandboxRoot;
+
+#[derive(Component)]
+struct UndoButton;
+
+#[derive(Component)]
+struct ClearButton;
+
+#[derive(Component)]
+struct SaveSwatchButton;
+
+#[derive(Component)]
+struct MenuButton;
+
+#[derive(Component)]
+struct SwatchRow;
+
+/// A saved swatch, by index in the personal palette.
+#[derive(Component)]
+struct SwatchButton(usize);
+
+fn load_personal_palette(
+    mut personal_palette: ResMut<PersonalPalette>,
+    storage: Option<Res<StorageBackend>>,
+) {
+    if let Some(storage) = storage {
+        if let Some(saved) = storage.load(SWATCHES_KEY) {
+            *personal_palette = saved;
+        }
+    }
+}
+
+fn spawn_swatches(
+    row: &mut ChildBuilder,
+    personal_palette: &PersonalPalette,
+    settings: &Settings,
+) {
+    for (index, swatch) in personal_palette.swatches.iter().enumerate() {
+        row.spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(40.0), Val::Px(40.0)),
+                margin: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            color: simulate_color(swatch.color, settings.color_vision).into(),
+            ..default()
+        })
+        .insert(SwatchButton(index));
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    mixing: Res<MixingModelKind>,
+    personal_palette: Res<PersonalPalette>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+) {
+    // NOTE: the menu only opens the sandbox once the pack is loaded
+    let palette: Vec<PaletteColor> =
+        packs.get(&active_pack.0).map_or_else(Vec::new, |pack| {
+            pack.palette.iter().chain(&pack.pigments).cloned().collect()
+        });
+    commands.insert_resource(SandboxState {
+        palette: palette.clone(),
+        mixing: *mixing,
+        selected: default(),
+        history: default(),
+    });
+
+    let undo_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Undo".into(),
+        },
+    );
+    commands.entity(undo_button).insert(UndoButton);
+
+    let clear_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Clear".into(),
+        },
+    );
+    commands.entity(clear_button).insert(ClearButton);
+
+    let save_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Save swatch".into(),
+        },
+    );
+    commands.entity(save_button).insert(SaveSwatchButton);
+
+    let menu_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Back to menu".into(),
+        },
+    );
+    commands.entity(menu_button).insert(MenuButton);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(SandboxRoot)
+        .with_children(|main_container| {
+            main_container.spawn_bundle(
+                TextBundle::from_section(
+                    "Sandbox",
+                    TextStyle {
+                        font: asset_server.load("edosz.ttf"),
+                        font_size: 45.0,
+                        color: Color::GREEN,
+                    },
+                )
+                .with_style(Style {
+                    align_self: AlignSelf::Center,
+                    ..default()
+                }),
+            );
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Percent(100.0),
+                            Val::Percent(50.0),
+                        ),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|board_section| {
+                    board_section
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(
+                                    Val::Percent(45.0),
+                                    Val::Percent(100.0),
+                                ),
+                                ..default()
+                            },
+                            color: Color::NONE.into(),
+                            ..default()
+                        })
+                        .insert(PlayerColor)
+                        .with_children(|panel| {
+                            spawn_color_readout(panel, &asset_server);
+                        });
+                });
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .add_child(undo_button)
+                .add_child(clear_button)
+                .add_child(save_button);
+
+            // NOTE: the extended palette doesn't fit on a single row
+            main_container
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|palette_row| {
+                    for (index, entry) in palette.iter().enumerate() {
+                        spawn_brush(
+                            palette_row,
+                            &asset_server,
+                            &settings,
+                            index,
+                            entry,
+                        );
+                    }
+                });
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        min_size: Size::new(Val::Auto, Val::Px(50.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .insert(SwatchRow)
+                .with_children(|row| {
+                    spawn_swatches(row, &personal_palette, &settings);
+                });
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .add_child(menu_button);
+        });
+}
+
+fn teardown(mut commands: Commands, query: Query<Entity, With<SandboxRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<SandboxState>();
+}
+
+// NOTE: the same filters as in a level, so only brush buttons add paint
+#[allow(clippy::type_complexity)]
+fn handle_color_clicked(
+    interaction_query: Query<
+        (&Interaction, &ColorSelector),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut sandbox: Option<ResMut<SandboxState>>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+) {
+    if let Some(sandbox) = sandbox.as_mut() {
+        for (interaction, color_selection) in &interaction_query {
+            if *interaction == Interaction::Clicked {
+                sandbox.select(color_selection.index);
+                play_effect(
+                    &audio,
+                    &asset_server,
+                    &settings,
+                    "audio/click.ogg",
+                );
+            }
+        }
+    }
+}
+
+fn handle_color_keys(
+    actions: Res<ActionState>,
+    mut sandbox: Option<ResMut<SandboxState>>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+) {
+    if let Some(sandbox) = sandbox.as_mut() {
+        for index in 0..sandbox.palette.len() {
+            if actions.just_pressed(Action::SelectColor(index)) {
+                sandbox.select(index);
+                play_effect(
+                    &audio,
+                    &asset_server,
+                    &settings,
+                    "audio/click.ogg",
+                );
+            }
+        }
+    }
+}
+
+fn handle_undo(
+    actions: Res<ActionState>,
+    mut sandbox: Option<ResMut<SandboxState>>,
+    query: Query<&Interaction, (With<UndoButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked || actions.just_pressed(Action::Undo) {
+        if let Some(sandbox) = sandbox.as_mut() {
+            sandbox.undo();
+        }
+    }
+}
+
+fn handle_clear(
+    actions: Res<ActionState>,
+    mut sandbox: Option<ResMut<SandboxState>>,
+    query: Query<&Interaction, (With<ClearButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked || actions.just_pressed(Action::Reset) {
+        if let Some(sandbox) = sandbox.as_mut() {
+            sandbox.clear();
+        }
+    }
+}
+
+fn save_swatch(
+    sandbox: Option<Res<SandboxState>>,
+    mut personal_palette: ResMut<PersonalPalette>,
+    storage: Option<Res<StorageBackend>>,
+    query: Query<&Interaction, (With<SaveSwatchButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    let sandbox = match sandbox {
+        Some(sandbox) => sandbox,
+        None => return,
+    };
+    if !clicked || sandbox.selected.is_empty() {
+        return;
+    }
+
+    if personal_palette.add(sandbox.swatch()) {
+        if let Some(storage) = storage {
+            storage.save(SWATCHES_KEY, &*personal_palette);
+        }
+    }
+}
+
+fn load_swatch(
+    mut sandbox: Option<ResMut<SandboxState>>,
+    personal_palette: Res<PersonalPalette>,
+    query: Query<(&Interaction, &SwatchButton), Changed<Interaction>>,
+) {
+    let sandbox = match sandbox.as_mut() {
+        Some(sandbox) => sandbox,
+        None => return,
+    };
+
+    for (interaction, swatch_button) in query.iter() {
+        let swatch = personal_palette.swatches.get(swatch_button.0);
+        if let (Interaction::Clicked, Some(swatch)) = (interaction, swatch) {
+            sandbox.restore(swatch);
+        }
+    }
+}
+
+fn update_mix(
+    settings: Res<Settings>,
+    sandbox: Option<Res<SandboxState>>,
+    mut panel_query: Query<&mut UiColor, With<PlayerColor>>,
+    mut readout_query: Query<(&mut Style, &mut Text), With<ColorReadout>>,
+) {
+    let sandbox = match sandbox {
+        Some(sandbox) => sandbox,
+        None => return,
+    };
+    if !sandbox.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let (color, description) = match sandbox.selected.is_empty() {
+        true => (Color::NONE, "Pick a color".to_string()),
+        false => (sandbox.color(), describe_color(sandbox.color())),
+    };
+    let displayed = simulate_color(color, settings.color_vision);
+    for mut ui_color in panel_query.iter_mut() {
+        ui_color.0 = displayed;
+    }
+    // NOTE: unlike in game, the hex code is always shown
+    for (mut style, mut text) in readout_query.iter_mut() {
+        style.display = Display::Flex;
+        text.sections[0].value = description.clone();
+        text.sections[0].style.color = match sandbox.selected.is_empty() {
+            true => Color::WHITE,
+            false => contrasting_text(displayed),
+        };
+    }
+}
+
+fn rebuild_swatches(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    personal_palette: Res<PersonalPalette>,
+    row_query: Query<Entity, With<SwatchRow>>,
+    swatch_query: Query<Entity, With<SwatchButton>>,
+) {
+    if !personal_palette.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for entity in swatch_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for row in row_query.iter() {
+        commands.entity(row).with_children(|row| {
+            spawn_swatches(row, &personal_palette, &settings);
+        });
+    }
+}
+
+fn back_to_menu(
+    mut app_state: ResMut<State<AppState>>,
+    mut actions: ResMut<ActionState>,
+    query: Query<&Interaction, (With<MenuButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked || actions.just_pressed(Action::Menu) {
+        actions.consume(Action::Menu);
+        app_state.set(AppState::MainMenu).unwrap();
+    }
+}
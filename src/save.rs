@@ -0,0 +1,350 @@
+use bevy::{app::AppExit, prelude::*, window::WindowCloseRequested};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    daily::DailyRun,
+    game::{
+        GameLostEvent, GameMode, GameState, GameWonEvent, LevelClock,
+        LevelState, RunSeed, StartLevelEvent,
+    },
+    settings::DifficultyRules,
+    storage::StorageBackend,
+    AppState,
+};
+
+const RUN_KEY: &str = "run";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SavedRun>()
+            .add_startup_system(load_saved_run)
+            // NOTE: levels are inserted with commands during the update, so
+            // they are only up to date in the next stage.
+            .add_system_to_stage(CoreStage::PostUpdate, save_on_level_start)
+            .add_system_to_stage(CoreStage::PostUpdate, save_on_close)
+            .add_system_to_stage(CoreStage::PostUpdate, clear_on_game_over)
+            .add_system_set(
+                SystemSet::on_exit(AppState::InGame).with_system(save_on_exit),
+            );
+    }
+}
+
+/// Everything needed to resume a run where it was left.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunSnapshot {
+    pub mode: GameMode,
+    pub level_index: u32,
+    pub lives_remaining: u32,
+    pub score: u32,
+    pub retries: u32,
+    /// Brush clicks made on the current level, as palette index and parts.
+    pub selection: Vec<(usize, u32)>,
+    pub amount_index: usize,
+    /// Seconds spent on the current level.
+    #[serde(default)]
+    pub elapsed: f32,
+    /// Seconds left on the countdown of the current level, in timed mode.
+    #[serde(default)]
+    pub time_left: Option<f32>,
+    #[serde(default)]
+    pub hints: u32,
+    /// Objective colors revealed by hints on the current level.
+    #[serde(default)]
+    pub revealed: Vec<usize>,
+    /// Seed of the run, for modes that generate their levels or swatches.
+    pub seed: Option<u64>,
+    pub daily: Option<DailyRun>,
+    /// Rules the run was started with.
+    #[serde(default)]
+    pub difficulty: Option<DifficultyRules>,
+}
+
+impl RunSnapshot {
+    pub fn capture(
+        mode: GameMode,
+        game: &GameState,
+        level: &LevelState,
+        clock: Option<&LevelClock>,
+        seed: Option<&RunSeed>,
+        daily: Option<&DailyRun>,
+        rules: Option<&DifficultyRules>,
+    ) -> Self {
+        Self {
+            mode,
+            level_index: level.level_index,
+            lives_remaining: game.lives_remaining,
+            score: game.score,
+            retries: level.retries,
+            selection: level.history.clone(),
+            amount_index: level.amount_index,
+            elapsed: clock.map_or(0.0, |clock| clock.elapsed),
+            time_left: clock.and_then(|clock| clock.time_left),
+            hints: level.hints,
+            revealed: level.revealed.clone(),
+            seed: seed.map(|seed| seed.0),
+            daily: daily.cloned(),
+            difficulty: rules.copied(),
+        }
+    }
+}
+
+/// The last saved run, which the main menu offers to continue.
+#[derive(Default)]
+pub struct SavedRun(pub Option<RunSnapshot>);
+
+/// Inserted when continuing a saved run, until its level is prepared.
+pub struct ResumeRun(pub RunSnapshot);
+
+fn load_saved_run(
+    mut saved_run: ResMut<SavedRun>,
+    storage: Option<Res<StorageBackend>>,
+) {
+    saved_run.0 = storage.and_then(|storage| storage.load(RUN_KEY));
+}
+
+fn save_run(
+    saved_run: &mut SavedRun,
+    storage: Option<&StorageBackend>,
+    snapshot: RunSnapshot,
+) {
+    if let Some(storage) = storage {
+        storage.save(RUN_KEY, &snapshot);
+    }
+    saved_run.0 = Some(snapshot);
+}
+
+/// Takes a snapshot of the current run, unless it can't be resumed as is.
+fn current_snapshot(
+    mode: GameMode,
+    game: Option<&GameState>,
+    level: Option<&LevelState>,
+    clock: Option<&LevelClock>,
+    seed: Option<&RunSeed>,
+    daily: Option<&DailyRun>,
+    rules: Option<&DifficultyRules>,
+) -> Option<RunSnapshot> {
+    let (game, level) = game.zip(level)?;
+    // NOTE: a solved level is about to move on, so the snapshot taken when it
+    // started is kept instead.
+    if game.is_lost() || (level.locked && level.is_color_found()) {
+        return None;
+    }
+
+    let mut snapshot =
+        RunSnapshot::capture(mode, game, level, clock, seed, daily, rules);
+    if level.locked {
+        // the wrong answer being shown is cleared once the alert ends
+        snapshot.selection.clear();
+    }
+    Some(snapshot)
+}
+
+// NOTE: saving reads every resource that a snapshot is made from
+#[allow(clippy::too_many_arguments)]
+fn save_on_level_start(
+    mut saved_run: ResMut<SavedRun>,
+    storage: Option<Res<StorageBackend>>,
+    mode: Res<GameMode>,
+    game: Option<Res<GameState>>,
+    level: Option<Res<LevelState>>,
+    clock: Option<Res<LevelClock>>,
+    seed: Option<Res<RunSeed>>,
+    daily: Option<Res<DailyRun>>,
+    rules: Option<Res<DifficultyRules>>,
+    mut start_evr: EventReader<StartLevelEvent>,
+) {
+    if start_evr.iter().count() < 1 {
+        return;
+    }
+
+    let snapshot = current_snapshot(
+        *mode,
+        game.as_deref(),
+        level.as_deref(),
+        clock.as_deref(),
+        seed.as_deref(),
+        daily.as_deref(),
+        rules.as_deref(),
+    );
+    if let Some(snapshot) = snapshot {
+        save_run(&mut saved_run, storage.as_deref(), snapshot);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_on_exit(
+    mut saved_run: ResMut<SavedRun>,
+    storage: Option<Res<StorageBackend>>,
+    mode: Res<GameMode>,
+    game: Option<Res<GameState>>,
+    level: Option<Res<LevelState>>,
+    clock: Option<Res<LevelClock>>,
+    seed: Option<Res<RunSeed>>,
+    daily: Option<Res<DailyRun>>,
+    rules: Option<Res<DifficultyRules>>,
+) {
+    let snapshot = current_snapshot(
+        *mode,
+        game.as_deref(),
+        level.as_deref(),
+        clock.as_deref(),
+        seed.as_deref(),
+        daily.as_deref(),
+        rules.as_deref(),
+    );
+    if let Some(snapshot) = snapshot {
+        save_run(&mut saved_run, storage.as_deref(), snapshot);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_on_close(
+    mut saved_run: ResMut<SavedRun>,
+    storage: Option<Res<StorageBackend>>,
+    mode: Res<GameMode>,
+    game: Option<Res<GameState>>,
+    level: Option<Res<LevelState>>,
+    clock: Option<Res<LevelClock>>,
+    seed: Option<Res<RunSeed>>,
+    daily: Option<Res<DailyRun>>,
+    rules: Option<Res<DifficultyRules>>,
+    mut close_evr: EventReader<WindowCloseRequested>,
+    mut exit_evr: EventReader<AppExit>,
+) {
+    let closing = close_evr.iter().count() + exit_evr.iter().count() > 0;
+    if !closing {
+        return;
+    }
+
+    let snapshot = current_snapshot(
+        *mode,
+        game.as_deref(),
+        level.as_deref(),
+        clock.as_deref(),
+        seed.as_deref(),
+        daily.as_deref(),
+        rules.as_deref(),
+    );
+    if let Some(snapshot) = snapshot {
+        save_run(&mut saved_run, storage.as_deref(), snapshot);
+    }
+}
+
+fn clear_on_game_over(
+    mut saved_run: ResMut<SavedRun>,
+    storage: Option<Res<StorageBackend>>,
+    mut won_evr: EventReader<GameWonEvent>,
+    mut lost_evr: EventReader<GameLostEvent>,
+) {
+    let finished = won_evr.iter().count() + lost_evr.iter().count() > 0;
+    if !finished {
+        return;
+    }
+
+    if let Some(storage) = storage {
+        storage.remove(RUN_KEY);
+    }
+    saved_run.0 = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color_mixer::MixingModelKind,
+        level_pack::{BrushAmount, LevelDefinition, PaletteColor},
+        recipe::Recipe,
+        settings::Difficulty,
+        storage::MemoryStorage,
+    };
+
+    fn level() -> LevelState {
+        let palette = [Color::RED, Color::YELLOW, Color::BLUE]
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| PaletteColor {
+                name: format!("Color {}", index),
+                color,
+            })
+            .collect();
+        let definition = LevelDefinition {
+            palette,
+            objective: Recipe::from_indices([0, 1, 1]),
+            matching: default(),
+            amounts: vec![BrushAmount::drop()],
+        };
+        LevelState::new(4, definition, MixingModelKind::default())
+    }
+
+    fn snapshot() -> RunSnapshot {
+        let game = GameState {
+            lives_remaining: 2,
+            score: 480,
+        };
+        let mut level = level();
+        level.retries = 1;
+        level.hint();
+        level.select(1);
+        level.select(2);
+        let mut clock = LevelClock::default();
+        clock.set_time_limit(45.0);
+        clock.elapsed = 12.5;
+        clock.time_left = Some(32.5);
+
+        RunSnapshot::capture(
+            GameMode::Timed,
+            &game,
+            &level,
+            Some(&clock),
+            Some(&RunSeed(99)),
+            None,
+            Some(&Difficulty::Hard.preset()),
+        )
+    }
+
+    #[test]
+    fn snapshots_round_trip_through_storage() {
+        let storage = StorageBackend::new(MemoryStorage::default());
+        let mut saved_run = SavedRun::default();
+        save_run(&mut saved_run, Some(&storage), snapshot());
+
+        let loaded: RunSnapshot = storage.load(RUN_KEY).unwrap();
+        assert_eq!(loaded.mode, GameMode::Timed);
+        assert_eq!(loaded.level_index, 4);
+        assert_eq!((loaded.lives_remaining, loaded.score), (2, 480));
+        assert_eq!(loaded.retries, 1);
+        assert_eq!(loaded.selection, vec![(1, 1), (2, 1)]);
+        assert_eq!((loaded.elapsed, loaded.time_left), (12.5, Some(32.5)));
+        assert_eq!((loaded.hints, loaded.revealed), (1, vec![0]));
+        assert_eq!(loaded.seed, Some(99));
+        assert_eq!(loaded.difficulty, Some(Difficulty::Hard.preset()));
+
+        let mut resumed = level();
+        resumed.restore_selection(&loaded.selection);
+        assert_eq!(resumed.selected, Recipe::from_indices([1, 2]));
+        assert_eq!(resumed.clicks, 2);
+    }
+
+    #[test]
+    fn game_over_clears_the_saved_run() {
+        let storage = StorageBackend::new(MemoryStorage::default());
+        let mut saved_run = SavedRun::default();
+        save_run(&mut saved_run, Some(&storage), snapshot());
+
+        let mut world = World::new();
+        world.insert_resource(saved_run);
+        world.insert_resource(storage);
+        world.insert_resource(Events::<GameWonEvent>::default());
+        let mut lost = Events::<GameLostEvent>::default();
+        lost.send(GameLostEvent);
+        world.insert_resource(lost);
+
+        SystemStage::single(clear_on_game_over).run(&mut world);
+
+        assert!(world.resource::<SavedRun>().0.is_none());
+        let storage = world.resource::<StorageBackend>();
+        assert!(storage.load::<RunSnapshot>(RUN_KEY).is_none());
+    }
+}
@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{color_vision::ColorVision, storage::StorageBackend};
+
+const SETTINGS_KEY: &str = "settings";
+
+/// Version of the [`Settings`] schema, bumped whenever a saved field changes
+/// meaning so that older files can be migrated.
+pub const SETTINGS_VERSION: u32 = 1;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .add_startup_system(load_settings)
+            .add_system(save_settings);
+    }
+}
+
+/// Player options, saved to storage whenever they change.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub mute: bool,
+    /// Volume of every sound, from 0.0 to 1.0.
+    pub master_volume: f32,
+    /// Volume of sound effects relative to the master volume.
+    pub effects_volume: f32,
+    /// Whether mixes are only judged when submitted, instead of as soon as
+    /// they have as many clicks as the level's complexity.
+    pub submit_mix: bool,
+    /// Whether brushes are labeled and colors are described in numbers.
+    pub colorblind: bool,
+    /// Color vision that displayed colors are filtered to simulate.
+    pub color_vision: ColorVision,
+    pub difficulty: Difficulty,
+    /// Rules used when the difficulty is [`Difficulty::Custom`].
+    pub custom_difficulty: DifficultyRules,
+    pub language: Language,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            mute: false,
+            master_volume: 1.0,
+            effects_volume: 1.0,
+            submit_mix: false,
+            colorblind: false,
+            color_vision: default(),
+            difficulty: default(),
+            custom_difficulty: Difficulty::Normal.preset(),
+            language: default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Volume that sound effects should be played at.
+    pub fn effects_volume(&self) -> f32 {
+        match self.mute {
+            true => 0.0,
+            false => self.master_volume * self.effects_volume,
+        }
+    }
+
+    /// Rules of the chosen difficulty.
+    pub fn difficulty_rules(&self) -> DifficultyRules {
+        match self.difficulty {
+            Difficulty::Custom => self.custom_difficulty,
+            preset => preset.preset(),
+        }
+    }
+
+    /// Brings settings saved by an older version of the game up to date.
+    fn migrate(mut self) -> Self {
+        // NOTE: files saved before versioning have no version field and
+        // deserialize as version 1, the first schema.
+        if self.version > SETTINGS_VERSION {
+            warn!(
+                "Settings were saved by a newer version ({}), using defaults",
+                self.version
+            );
+            return default();
+        }
+
+        self.master_volume = self.master_volume.clamp(0.0, 1.0);
+        self.effects_volume = self.effects_volume.clamp(0.0, 1.0);
+        self.custom_difficulty = self.custom_difficulty.validated();
+        self.version = SETTINGS_VERSION;
+        self
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    /// Uses the rules picked in the settings.
+    Custom,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Custom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Custom => "Custom",
+        }
+    }
+
+    /// The difficulty that follows this one in the menu, wrapping around.
+    pub fn next(&self) -> Difficulty {
+        let index = Self::ALL.iter().position(|d| d == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Rules of the preset. Custom starts from the normal rules.
+    pub fn preset(&self) -> DifficultyRules {
+        match self {
+            Difficulty::Easy => DifficultyRules {
+                starting_lives: 3,
+                max_lives: 5,
+                lives_per_success: 1,
+                failure: FailurePenalty::RetryLevel,
+                hint_cost: HintCost::Points(50),
+            },
+            Difficulty::Normal | Difficulty::Custom => DifficultyRules {
+                starting_lives: 1,
+                max_lives: 3,
+                lives_per_success: 1,
+                failure: FailurePenalty::RetryLevel,
+                hint_cost: HintCost::Points(50),
+            },
+            Difficulty::Hard => DifficultyRules {
+                starting_lives: 1,
+                max_lives: 2,
+                lives_per_success: 1,
+                failure: FailurePenalty::RestartRun,
+                hint_cost: HintCost::Life,
+            },
+        }
+    }
+}
+
+/// What happens after a wrong answer, besides losing a life.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum FailurePenalty {
+    /// The level starts over.
+    #[default]
+    RetryLevel,
+    /// The run starts over from its first level, with a score of zero.
+    RestartRun,
+}
+
+/// What a hint costs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HintCost {
+    /// Points taken from the score.
+    Points(u32),
+    /// A life, which can't be the last one.
+    Life,
+}
+
+impl Default for HintCost {
+    fn default() -> Self {
+        HintCost::Points(50)
+    }
+}
+
+/// Lives and failures of a run, set by the difficulty when the run starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DifficultyRules {
+    pub starting_lives: u32,
+    pub max_lives: u32,
+    /// Lives gained by solving a level, up to the maximum.
+    pub lives_per_success: u32,
+    pub failure: FailurePenalty,
+    /// What a hint costs, points in rules saved before this was a choice.
+    #[serde(default)]
+    pub hint_cost: HintCost,
+}
+
+impl DifficultyRules {
+    /// Makes sure a run can start, with at least one life.
+    pub fn validated(mut self) -> Self {
+        self.starting_lives = self.starting_lives.max(1);
+        self.max_lives = self.max_lives.max(self.starting_lives);
+        self
+    }
+}
+
+/// Language of the game's text. Only English is available for now.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum Language {
+    #[default]
+    English,
+}
+
+/// Plays a sound effect at the volume chosen in the settings.
+pub fn play_effect(
+    audio: &Audio,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    path: &str,
+) {
+    let volume = settings.effects_volume();
+    if volume > 0.0 {
+        audio.play_with_settings(
+            asset_server.load(path),
+            PlaybackSettings::ONCE.with_volume(volume),
+        );
+    }
+}
+
+fn load_settings(
+    mut settings: ResMut<Settings>,
+    storage: Option<Res<StorageBackend>>,
+) {
+    // NOTE: corrupt files are ignored by the storage, leaving the defaults
+    let saved: Option<Settings> =
+        storage.and_then(|storage| storage.load(SETTINGS_KEY));
+    if let Some(saved) = saved {
+        *settings = saved.migrate();
+    }
+}
+
+fn save_settings(
+    settings: Res<Settings>,
+    storage: Option<Res<StorageBackend>>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    if let Some(storage) = storage {
+        storage.save(SETTINGS_KEY, &*settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStorage, Storage};
+
+    fn load(saved: &str) -> Settings {
+        let storage = MemoryStorage::default();
+        storage.write(SETTINGS_KEY, saved).unwrap();
+
+        let mut world = World::new();
+        world.init_resource::<Settings>();
+        world.insert_resource(StorageBackend::new(storage));
+        SystemStage::single(load_settings).run(&mut world);
+        world.remove_resource::<Settings>().unwrap()
+    }
+
+    #[test]
+    fn version_one_settings_are_migrated() {
+        // NOTE: saved before the hint cost and color vision were settings
+        let settings = load(
+            "(version:1,mute:false,master_volume:1.5,effects_volume:0.5,\
+             submit_mix:true,colorblind:true,difficulty:Custom,\
+             custom_difficulty:(starting_lives:0,max_lives:0,\
+             lives_per_success:2,failure:RestartRun))",
+        );
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.master_volume, 1.0);
+        assert_eq!(settings.effects_volume, 0.5);
+        assert!(settings.submit_mix);
+        assert!(settings.colorblind);
+        assert_eq!(settings.color_vision, ColorVision::default());
+        assert_eq!(
+            settings.difficulty_rules(),
+            DifficultyRules {
+                starting_lives: 1,
+                max_lives: 1,
+                lives_per_success: 2,
+                failure: FailurePenalty::RestartRun,
+                hint_cost: HintCost::Points(50),
+            }
+        );
+    }
+
+    #[test]
+    fn unreadable_settings_are_ignored() {
+        assert_eq!(load("not settings at all {"), Settings::default());
+        assert_eq!(load("(version:99,mute:true)"), Settings::default());
+    }
+}
@@ -0,0 +1,486 @@
+use bevy::prelude::*;
+
+use crate::{
+    input::{save_input_map, Action, ActionState, Binding, InputMap},
+    settings::{FailurePenalty, HintCost, Settings},
+    storage::StorageBackend,
+    widgets::{spawn_game_button, GameButton},
+    AppState,
+};
+
+/// Settings screen, pushed over the state it was opened from.
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Settings).with_system(setup),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Settings).with_system(teardown),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Settings)
+                    // NOTE: the input that starts a rebinding must not be
+                    // captured as the new binding.
+                    .with_system(capture_binding.before(handle_binding_clicked))
+                    .with_system(handle_binding_clicked)
+                    .with_system(update_binding_buttons)
+                    .with_system(reset_bindings)
+                    .with_system(handle_option_clicked)
+                    .with_system(update_option_buttons)
+                    .with_system(back.before(capture_binding)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+struct OptionButton(SettingOption);
+
+#[derive(Component)]
+struct BindingButton(Action);
+
+#[derive(Component)]
+struct DefaultsButton;
+
+#[derive(Component)]
+struct BackButton;
+
+#[derive(Component)]
+struct MessageText;
+
+/// A setting that is changed by clicking its button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SettingOption {
+    Sound,
+    Volume,
+    Judging,
+    Labels,
+    Vision,
+    StartingLives,
+    MaxLives,
+    LivesPerSuccess,
+    Failure,
+    HintCost,
+}
+
+/// Largest number of lives that the custom difficulty can give.
+const MAX_CUSTOM_LIVES: u32 = 9;
+
+impl SettingOption {
+    const ALL: [SettingOption; 10] = [
+        SettingOption::Sound,
+        SettingOption::Volume,
+        SettingOption::Judging,
+        SettingOption::Labels,
+        SettingOption::Vision,
+        SettingOption::StartingLives,
+        SettingOption::MaxLives,
+        SettingOption::LivesPerSuccess,
+        SettingOption::Failure,
+        SettingOption::HintCost,
+    ];
+
+    fn text(&self, settings: &Settings) -> String {
+        match self {
+            SettingOption::Sound => match settings.mute {
+                true => "Sound: off".into(),
+                false => "Sound: on".into(),
+            },
+            SettingOption::Volume => {
+                format!("Volume: {:.0}%", settings.master_volume * 100.0)
+            }
+            SettingOption::Judging => match settings.submit_mix {
+                true => "Judge on submit".into(),
+                false => "Judge on last click".into(),
+            },
+            SettingOption::Labels => match settings.colorblind {
+                true => "Color labels: on".into(),
+                false => "Color labels: off".into(),
+            },
+            SettingOption::Vision => {
+                format!("Vision: {}", settings.color_vision.name())
+            }
+            SettingOption::StartingLives => format!(
+                "Custom starting lives: {}",
+                settings.custom_difficulty.starting_lives
+            ),
+            SettingOption::MaxLives => format!(
+                "Custom max lives: {}",
+                settings.custom_difficulty.max_lives
+            ),
+            SettingOption::LivesPerSuccess => format!(
+                "Custom lives per success: {}",
+                settings.custom_difficulty.lives_per_success
+            ),
+            SettingOption::Failure => {
+                match settings.custom_difficulty.failure {
+                    FailurePenalty::RetryLevel => "Custom failure: retry level",
+                    FailurePenalty::RestartRun => "Custom failure: restart run",
+                }
+                .into()
+            }
+            SettingOption::HintCost => {
+                match settings.custom_difficulty.hint_cost {
+                    HintCost::Points(points) => {
+                        format!("Custom hint cost: {} points", points)
+                    }
+                    HintCost::Life => "Custom hint cost: a life".into(),
+                }
+            }
+        }
+    }
+
+    fn change(&self, settings: &mut Settings) {
+        match self {
+            SettingOption::Sound => settings.mute = !settings.mute,
+            SettingOption::Volume => {
+                // NOTE: cycles through 25%, 50%, 75% and 100%
+                let step = (settings.master_volume * 4.0).round() as u32 % 4;
+                settings.master_volume = (step + 1) as f32 / 4.0;
+            }
+            SettingOption::Judging => {
+                settings.submit_mix = !settings.submit_mix
+            }
+            SettingOption::Labels => settings.colorblind = !settings.colorblind,
+            SettingOption::Vision => {
+                settings.color_vision = settings.color_vision.next()
+            }
+            SettingOption::StartingLives => {
+                let rules = &mut settings.custom_difficulty;
+                rules.starting_lives =
+                    rules.starting_lives % MAX_CUSTOM_LIVES + 1;
+            }
+            SettingOption::MaxLives => {
+                let rules = &mut settings.custom_difficulty;
+                rules.max_lives = rules.max_lives % MAX_CUSTOM_LIVES + 1;
+                // NOTE: wraps around to the starting lives, below which the
+                // maximum can't go
+                if rules.max_lives < rules.starting_lives {
+                    rules.max_lives = rules.starting_lives;
+                }
+            }
+            SettingOption::LivesPerSuccess => {
+                let rules = &mut settings.custom_difficulty;
+                rules.lives_per_success = (rules.lives_per_success + 1) % 3;
+            }
+            SettingOption::Failure => {
+                let rules = &mut settings.custom_difficulty;
+                rules.failure = match rules.failure {
+                    FailurePenalty::RetryLevel => FailurePenalty::RestartRun,
+                    FailurePenalty::RestartRun => FailurePenalty::RetryLevel,
+                };
+            }
+            SettingOption::HintCost => {
+                let rules = &mut settings.custom_difficulty;
+                rules.hint_cost = match rules.hint_cost {
+                    HintCost::Points(_) => HintCost::Life,
+                    HintCost::Life => HintCost::default(),
+                };
+            }
+        }
+        settings.custom_difficulty = settings.custom_difficulty.validated();
+    }
+}
+
+/// The action waiting for a new binding, if any.
+#[derive(Default)]
+struct Rebinding(Option<Action>);
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    input_map: Res<InputMap>,
+    settings: Res<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    rebinding.0 = None;
+
+    let option_buttons: Vec<Entity> = SettingOption::ALL
+        .iter()
+        .map(|option| {
+            let button = spawn_game_button(
+                &mut commands,
+                &asset_server,
+                GameButton {
+                    text: option.text(&settings),
+                },
+            );
+            commands.entity(button).insert(OptionButton(*option));
+            button
+        })
+        .collect();
+
+    let binding_buttons: Vec<Entity> = Action::all()
+        .map(|action| {
+            let button = spawn_game_button(
+                &mut commands,
+                &asset_server,
+                GameButton {
+                    text: binding_text(action, &input_map),
+                },
+            );
+            commands.entity(button).insert(BindingButton(action));
+            button
+        })
+        .collect();
+
+    let defaults_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Reset controls".into(),
+        },
+    );
+    commands.entity(defaults_button).insert(DefaultsButton);
+
+    let back_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Back".into(),
+        },
+    );
+    commands.entity(back_button).insert(BackButton);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(SettingsMenu)
+        .with_children(|main_container| {
+            main_container.spawn_bundle(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font: asset_server.load("edosz.ttf"),
+                    font_size: 45.0,
+                    color: Color::GREEN,
+                },
+            ));
+
+            main_container
+                .spawn_bundle(TextBundle::from_section(
+                    "Click an action, then press its new key or button",
+                    TextStyle {
+                        font: asset_server.load("edosz.ttf"),
+                        font_size: 22.0,
+                        color: Color::YELLOW_GREEN,
+                    },
+                ))
+                .insert(MessageText);
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        justify_content: JustifyContent::Center,
+                        flex_wrap: FlexWrap::Wrap,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .push_children(&option_buttons);
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        size: Size::new(
+                            Val::Percent(100.0),
+                            Val::Percent(70.0),
+                        ),
+                        flex_direction: FlexDirection::ColumnReverse,
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::FlexStart,
+                        align_content: AlignContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .push_children(&binding_buttons);
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .add_child(defaults_button)
+                .add_child(back_button);
+        });
+}
+
+fn teardown(mut commands: Commands, query: Query<Entity, With<SettingsMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn binding_text(action: Action, input_map: &InputMap) -> String {
+    format!("{}: {}", action.name(), input_map.bindings(action))
+}
+
+fn set_message(query: &mut Query<&mut Text, With<MessageText>>, text: String) {
+    for mut message in query.iter_mut() {
+        message.sections[0].value = text.clone();
+    }
+}
+
+fn handle_binding_clicked(
+    mut rebinding: ResMut<Rebinding>,
+    query: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+    mut message_query: Query<&mut Text, With<MessageText>>,
+) {
+    let clicked = query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Clicked);
+
+    if let Some((_, button)) = clicked {
+        rebinding.0 = Some(button.0);
+        set_message(
+            &mut message_query,
+            format!("Press a key or button for {}", button.0.name()),
+        );
+    }
+}
+
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut input_map: ResMut<InputMap>,
+    storage: Option<Res<StorageBackend>>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut message_query: Query<&mut Text, With<MessageText>>,
+) {
+    let action = match rebinding.0 {
+        Some(action) => action,
+        None => return,
+    };
+
+    let pressed_key = keys.get_just_pressed().next().copied().map(Binding::Key);
+    let pressed_button = buttons
+        .get_just_pressed()
+        .next()
+        .map(|button| Binding::Gamepad(button.button_type));
+    let binding = match pressed_key.or(pressed_button) {
+        Some(binding) => binding,
+        None => return,
+    };
+
+    rebinding.0 = None;
+    match input_map.bind(action, binding) {
+        Ok(()) => {
+            save_input_map(storage.as_deref(), &input_map);
+            set_message(
+                &mut message_query,
+                format!("{} bound to {}", action.name(), binding),
+            );
+        }
+        Err(conflict) => set_message(
+            &mut message_query,
+            format!("{} is {}", binding, conflict),
+        ),
+    }
+}
+
+fn handle_option_clicked(
+    mut settings: ResMut<Settings>,
+    query: Query<(&Interaction, &OptionButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            button.0.change(&mut settings);
+        }
+    }
+}
+
+fn update_option_buttons(
+    settings: Res<Settings>,
+    mut query: Query<(&mut GameButton, &OptionButton)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (mut button, option_button) in query.iter_mut() {
+        let text = option_button.0.text(&settings);
+        if button.text != text {
+            button.text = text;
+        }
+    }
+}
+
+fn update_binding_buttons(
+    input_map: Res<InputMap>,
+    rebinding: Res<Rebinding>,
+    mut query: Query<(&mut GameButton, &BindingButton)>,
+) {
+    if !input_map.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+
+    for (mut button, binding_button) in query.iter_mut() {
+        let action = binding_button.0;
+        let text = match rebinding.0 {
+            Some(rebinding) if rebinding == action => {
+                format!("{}: ...", action.name())
+            }
+            _ => binding_text(action, &input_map),
+        };
+
+        if button.text != text {
+            button.text = text;
+        }
+    }
+}
+
+fn reset_bindings(
+    mut input_map: ResMut<InputMap>,
+    storage: Option<Res<StorageBackend>>,
+    query: Query<&Interaction, (With<DefaultsButton>, Changed<Interaction>)>,
+    mut message_query: Query<&mut Text, With<MessageText>>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked {
+        *input_map = InputMap::default();
+        save_input_map(storage.as_deref(), &input_map);
+        set_message(&mut message_query, "Controls reset to defaults".into());
+    }
+}
+
+fn back(
+    mut app_state: ResMut<State<AppState>>,
+    rebinding: Res<Rebinding>,
+    mut actions: ResMut<ActionState>,
+    query: Query<&Interaction, (With<BackButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+    // NOTE: while rebinding, the menu's input is the new binding
+    let menu_pressed =
+        rebinding.0.is_none() && actions.just_pressed(Action::Menu);
+
+    if clicked || menu_pressed {
+        actions.consume(Action::Menu);
+        app_state.pop().unwrap();
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    color_distance::{ColorMetric, MatchRule},
+    color_mixer::MixingModelKind,
+    level_pack::PaletteColor,
+    recipe::Recipe,
+};
+
+/// A recipe that mixes into a color close to the one asked for.
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub recipe: Recipe,
+    pub color: Color,
+    /// Distance to the target, in the units of the metric used.
+    pub distance: f32,
+}
+
+/// Finds the recipes of a palette that make a given color.
+///
+/// Recipes are built up one part at a time, and a recipe stops growing once
+/// the parts it has left can't bring its mix back within the distance being
+/// looked for. Mixed colors are cached, so solving many colors with the same
+/// palette stays cheap.
+pub struct Solver {
+    palette: Vec<PaletteColor>,
+    mixing: MixingModelKind,
+    mixes: HashMap<Recipe, Color>,
+}
+
+impl Solver {
+    pub fn new(palette: Vec<PaletteColor>, mixing: MixingModelKind) -> Self {
+        Self {
+            palette,
+            mixing,
+            mixes: default(),
+        }
+    }
+
+    pub fn palette(&self) -> &[PaletteColor] {
+        &self.palette
+    }
+
+    /// Recipes of up to `max_complexity` parts that `rule` accepts as
+    /// `target`, closest first.
+    pub fn solve(
+        &mut self,
+        target: Color,
+        max_complexity: usize,
+        rule: &MatchRule,
+    ) -> Vec<Solution> {
+        // NOTE: the search is a little more lenient than the rule, which
+        // gets the final say.
+        let tolerance = rule.tolerance.max(0.0) + 0.0001;
+        let mut search = Search::new(
+            target,
+            max_complexity,
+            rule.metric,
+            tolerance,
+            usize::MAX,
+            &self.palette,
+        );
+        self.extend(&mut search, &Recipe::new(), 0);
+
+        let mut solutions = search.found;
+        solutions.retain(|solution| rule.is_match(solution.color, target));
+        solutions
+    }
+
+    /// The `limit` recipes of up to `max_complexity` parts that are closest to
+    /// `target`, sorted by their distance and then by complexity.
+    ///
+    /// Recipes that reduce to a smaller one are left out, since the smaller
+    /// recipe already mixes into the same color.
+    pub fn ranked(
+        &mut self,
+        target: Color,
+        max_complexity: usize,
+        metric: ColorMetric,
+        limit: usize,
+    ) -> Vec<Solution> {
+        let mut search = Search::new(
+            target,
+            max_complexity,
+            metric,
+            f32::INFINITY,
+            limit,
+            &self.palette,
+        );
+        self.extend(&mut search, &Recipe::new(), 0);
+        search.found
+    }
+
+    /// Visits every recipe made by adding parts from palette index `start`
+    /// onwards to `recipe`, so that each multiset is only visited once.
+    fn extend(&mut self, search: &mut Search, recipe: &Recipe, start: usize) {
+        for index in start..self.palette.len() {
+            let mut extended = recipe.clone();
+            extended.add(index);
+            let color = self.mix(&extended);
+            let distance = search.metric.distance(color, search.target);
+            let parts = extended.len() as usize;
+
+            if !extended.is_reducible() {
+                search.keep(Solution {
+                    recipe: extended.clone(),
+                    color,
+                    distance,
+                });
+            }
+            if search.can_extend(parts, distance) {
+                self.extend(search, &extended, index);
+            }
+        }
+    }
+
+    fn mix(&mut self, recipe: &Recipe) -> Color {
+        let palette = &self.palette;
+        let model = self.mixing.model();
+        *self
+            .mixes
+            .entry(recipe.clone())
+            .or_insert_with(|| recipe.mix(palette, model))
+    }
+}
+
+/// State of a search for the recipes closest to a target.
+struct Search {
+    target: Color,
+    max_complexity: usize,
+    metric: ColorMetric,
+    /// Distance past which recipes are never kept.
+    tolerance: f32,
+    /// Largest number of recipes kept.
+    limit: usize,
+    /// Distance from the target to the farthest palette color.
+    reach: f32,
+    /// Recipes kept so far, closest first.
+    found: Vec<Solution>,
+}
+
+impl Search {
+    fn new(
+        target: Color,
+        max_complexity: usize,
+        metric: ColorMetric,
+        tolerance: f32,
+        limit: usize,
+        palette: &[PaletteColor],
+    ) -> Self {
+        let reach = palette
+            .iter()
+            .map(|color| metric.distance(color.color, target))
+            .fold(0.0, f32::max);
+        Self {
+            target,
+            max_complexity,
+            metric,
+            tolerance,
+            limit,
+            reach,
+            found: Vec::new(),
+        }
+    }
+
+    /// Distance that a recipe must not exceed to be kept.
+    fn bound(&self) -> f32 {
+        match self.found.len() < self.limit {
+            true => self.tolerance,
+            false => self.found.last().map_or(self.tolerance, |worst| {
+                worst.distance.min(self.tolerance)
+            }),
+        }
+    }
+
+    /// Whether adding parts to a recipe of `parts` parts, whose mix is
+    /// `distance` away from the target, can still lead to a recipe within
+    /// the bound.
+    fn can_extend(&self, parts: usize, distance: f32) -> bool {
+        if parts >= self.max_complexity {
+            return false;
+        }
+
+        // NOTE: a mix lies between the colors it is made of, so the parts
+        // added bring it at most `reach` closer to the target, weighted by
+        // their share of the bigger recipe.
+        let parts = parts as f32;
+        let added = (self.max_complexity as f32) - parts;
+        let closest = (parts * distance - added * self.reach) / (parts + added);
+        closest <= self.bound()
+    }
+
+    fn keep(&mut self, solution: Solution) {
+        if solution.distance > self.bound() {
+            return;
+        }
+
+        let index = self.found.partition_point(|kept| {
+            kept.distance
+                .total_cmp(&solution.distance)
+                .then(kept.recipe.len().cmp(&solution.recipe.len()))
+                .is_le()
+        });
+        self.found.insert(index, solution);
+        self.found.truncate(self.limit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_generator::multisets;
+
+    const MAX_COMPLEXITY: usize = 4;
+    const LIMIT: usize = 10;
+
+    fn solver() -> Solver {
+        let palette = [
+            ("Red", Color::RED),
+            ("Yellow", Color::YELLOW),
+            ("Blue", Color::BLUE),
+            ("White", Color::WHITE),
+        ]
+        .into_iter()
+        .map(|(name, color)| PaletteColor {
+            name: name.into(),
+            color,
+        })
+        .collect();
+        Solver::new(palette, MixingModelKind::default())
+    }
+
+    fn target(solver: &Solver, recipe: &Recipe) -> Color {
+        recipe.mix(solver.palette(), solver.mixing.model())
+    }
+
+    #[test]
+    fn objective_comes_first() {
+        let mut solver = solver();
+        let objective = Recipe::from_indices([0, 1, 1, 2]);
+        let target = target(&solver, &objective);
+
+        let ranked =
+            solver.ranked(target, MAX_COMPLEXITY, ColorMetric::Oklab, LIMIT);
+        assert_eq!(ranked[0].recipe, objective);
+        assert!(ranked[0].distance < 1e-4);
+
+        let solutions =
+            solver.solve(target, MAX_COMPLEXITY, &MatchRule::strict());
+        assert_eq!(solutions[0].recipe, objective);
+        assert_eq!(solutions[0].distance, 0.0);
+    }
+
+    #[test]
+    fn reducible_recipes_are_left_out() {
+        let mut solver = solver();
+        let objective = Recipe::from_indices([0, 1]);
+        let target = target(&solver, &objective);
+
+        let ranked =
+            solver.ranked(target, MAX_COMPLEXITY, ColorMetric::Oklab, LIMIT);
+        assert!(ranked
+            .iter()
+            .all(|solution| !solution.recipe.is_reducible()));
+
+        let equivalents = ranked
+            .iter()
+            .filter(|solution| solution.recipe.equivalent(&objective))
+            .count();
+        assert_eq!(equivalents, 1);
+    }
+
+    #[test]
+    fn solutions_are_sorted_by_distance() {
+        let mut solver = solver();
+        let ranked = solver.ranked(
+            Color::GREEN,
+            MAX_COMPLEXITY,
+            ColorMetric::Ciede2000,
+            LIMIT,
+        );
+        assert_eq!(ranked.len(), LIMIT);
+        assert!(ranked
+            .windows(2)
+            .all(|pair| pair[0].distance <= pair[1].distance));
+    }
+
+    /// Distances of every irreducible recipe to `color`, found by mixing all
+    /// of them.
+    fn exhaustive(
+        solver: &Solver,
+        color: Color,
+        metric: ColorMetric,
+    ) -> Vec<f32> {
+        let mut distances: Vec<f32> = (1..=MAX_COMPLEXITY)
+            .flat_map(|size| multisets(solver.palette().len(), size))
+            .map(Recipe::from_indices)
+            .filter(|recipe| !recipe.is_reducible())
+            .map(|recipe| metric.distance(target(solver, &recipe), color))
+            .collect();
+        distances.sort_by(f32::total_cmp);
+        distances
+    }
+
+    #[test]
+    fn pruning_keeps_the_closest_recipes() {
+        let mut solver = solver();
+        let targets = [
+            Color::GREEN,
+            Color::ORANGE,
+            Color::rgb(0.4, 0.3, 0.6),
+            target(&solver, &Recipe::from_indices([0, 2, 2, 3])),
+        ];
+
+        for target in targets {
+            for metric in [ColorMetric::Oklab, ColorMetric::Ciede2000] {
+                let distances: Vec<f32> = solver
+                    .ranked(target, MAX_COMPLEXITY, metric, LIMIT)
+                    .iter()
+                    .map(|solution| solution.distance)
+                    .collect();
+                let mut expected = exhaustive(&solver, target, metric);
+                expected.truncate(LIMIT);
+                assert_eq!(distances, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn solutions_are_every_match() {
+        let mut solver = solver();
+        let target = target(&solver, &Recipe::from_indices([1, 2, 3]));
+        let rule = MatchRule {
+            metric: ColorMetric::Ciede2000,
+            tolerance: 15.0,
+        };
+
+        let solutions = solver.solve(target, MAX_COMPLEXITY, &rule);
+        let matches = exhaustive(&solver, target, rule.metric)
+            .into_iter()
+            .filter(|distance| *distance <= rule.tolerance)
+            .count();
+        assert!(matches > 1);
+        assert_eq!(solutions.len(), matches);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    daily::DailyDate,
+    game::{
+        GameLostEvent, GameMode, GameState, GameWonEvent, LevelClock,
+        LevelFailedEvent, LevelState, LevelSucceededEvent, TimeUpEvent,
+    },
+    level_pack::{ActiveLevelPack, LevelPack},
+    storage::StorageBackend,
+};
+
+const LEADERBOARD_KEY: &str = "leaderboard";
+const LEVEL_STATS_KEY: &str = "level_stats";
+
+/// Number of high scores kept for every game mode.
+pub const HIGH_SCORES_PER_MODE: usize = 10;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Leaderboard>()
+            .init_resource::<LevelStats>()
+            .add_startup_system(load_stats)
+            // NOTE: levels are inserted with commands during the update, so
+            // they are only up to date in the next stage.
+            .add_system_to_stage(CoreStage::PostUpdate, record_level_outcome)
+            .add_system_to_stage(CoreStage::PostUpdate, record_high_score);
+    }
+}
+
+/// The final score of a run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub mode: GameMode,
+    pub score: u32,
+    pub levels_solved: u32,
+    pub date: DailyDate,
+}
+
+/// Best scores of finished runs, saved to storage.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Leaderboard {
+    scores: Vec<HighScore>,
+}
+
+impl Leaderboard {
+    /// Best scores of `mode`, highest first.
+    pub fn top(&self, mode: GameMode) -> impl Iterator<Item = &HighScore> {
+        self.scores.iter().filter(move |score| score.mode == mode)
+    }
+
+    /// Adds `score` if it is among the best of its mode. Returns its rank,
+    /// starting at 0.
+    pub fn insert(&mut self, score: HighScore) -> Option<usize> {
+        let rank = self
+            .top(score.mode)
+            .take_while(|other| other.score >= score.score)
+            .count();
+        if rank >= HIGH_SCORES_PER_MODE {
+            return None;
+        }
+
+        // scores are kept sorted, so the new one goes before the first lower
+        // score of any mode
+        let index = self
+            .scores
+            .iter()
+            .position(|other| other.score < score.score)
+            .unwrap_or(self.scores.len());
+        let mode = score.mode;
+        self.scores.insert(index, score);
+
+        let mut kept = 0;
+        self.scores.retain(|other| {
+            if other.mode != mode {
+                return true;
+            }
+            kept += 1;
+            kept <= HIGH_SCORES_PER_MODE
+        });
+        Some(rank)
+    }
+}
+
+/// How a campaign level went over every run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelRecord {
+    /// Number of answers given, right or wrong.
+    pub attempts: u32,
+    pub solves: u32,
+    /// Number of runs where the level was solved without a wrong answer.
+    pub first_try_solves: u32,
+    /// Fewest seconds taken to solve the level.
+    pub best_time: Option<f32>,
+}
+
+/// Statistics of campaign levels, by level pack name and level index.
+///
+/// Generated levels are never played twice, so they aren't recorded.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LevelStats {
+    packs: HashMap<String, HashMap<u32, LevelRecord>>,
+}
+
+impl LevelStats {
+    pub fn record(&self, pack: &str, level_index: u32) -> Option<&LevelRecord> {
+        self.packs
+            .get(pack)
+            .and_then(|levels| levels.get(&level_index))
+    }
+
+    fn record_mut(&mut self, pack: &str, level_index: u32) -> &mut LevelRecord {
+        self.packs
+            .entry(pack.to_string())
+            .or_default()
+            .entry(level_index)
+            .or_default()
+    }
+}
+
+fn load_stats(
+    mut leaderboard: ResMut<Leaderboard>,
+    mut level_stats: ResMut<LevelStats>,
+    storage: Option<Res<StorageBackend>>,
+) {
+    if let Some(storage) = storage {
+        if let Some(saved) = storage.load(LEADERBOARD_KEY) {
+            *leaderboard = saved;
+        }
+        if let Some(saved) = storage.load(LEVEL_STATS_KEY) {
+            *level_stats = saved;
+        }
+    }
+}
+
+// NOTE: a level ends with one of several events, each read separately
+#[allow(clippy::too_many_arguments)]
+fn record_level_outcome(
+    mut level_stats: ResMut<LevelStats>,
+    storage: Option<Res<StorageBackend>>,
+    mode: Res<GameMode>,
+    level: Option<Res<LevelState>>,
+    clock: Option<Res<LevelClock>>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+    mut succeeded_evr: EventReader<LevelSucceededEvent>,
+    mut won_evr: EventReader<GameWonEvent>,
+    mut failed_evr: EventReader<LevelFailedEvent>,
+    mut lost_evr: EventReader<GameLostEvent>,
+    mut time_up_evr: EventReader<TimeUpEvent>,
+) {
+    let solved = succeeded_evr.iter().count() + won_evr.iter().count() > 0;
+    let failed = failed_evr.iter().count()
+        + lost_evr.iter().count()
+        + time_up_evr.iter().count()
+        > 0;
+    if (!solved && !failed) || *mode != GameMode::Campaign {
+        return;
+    }
+
+    let (level, pack) = match (level, packs.get(&active_pack.0)) {
+        (Some(level), Some(pack)) => (level, pack),
+        _ => return,
+    };
+
+    let record = level_stats.record_mut(&pack.name, level.level_index);
+    record.attempts += 1;
+    if solved {
+        record.solves += 1;
+        if level.retries == 0 {
+            record.first_try_solves += 1;
+        }
+        let elapsed = clock.map_or(0.0, |clock| clock.elapsed);
+        record.best_time = Some(match record.best_time {
+            Some(best_time) => best_time.min(elapsed),
+            None => elapsed,
+        });
+    }
+
+    if let Some(storage) = storage {
+        storage.save(LEVEL_STATS_KEY, &*level_stats);
+    }
+}
+
+fn record_high_score(
+    mut leaderboard: ResMut<Leaderboard>,
+    storage: Option<Res<StorageBackend>>,
+    mode: Res<GameMode>,
+    game: Option<Res<GameState>>,
+    level: Option<Res<LevelState>>,
+    mut won_evr: EventReader<GameWonEvent>,
+    mut lost_evr: EventReader<GameLostEvent>,
+) {
+    let won = won_evr.iter().count() > 0;
+    let lost = lost_evr.iter().count() > 0;
+    if !won && !lost {
+        return;
+    }
+
+    let (game, level) = match (game, level) {
+        (Some(game), Some(level)) => (game, level),
+        _ => return,
+    };
+
+    let levels_solved = match won {
+        true => level.level_index + 1,
+        false => level.level_index,
+    };
+    let rank = leaderboard.insert(HighScore {
+        mode: *mode,
+        score: game.score,
+        levels_solved,
+        date: DailyDate::today(),
+    });
+
+    if let (Some(_), Some(storage)) = (rank, storage) {
+        storage.save(LEADERBOARD_KEY, &*leaderboard);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high_score(mode: GameMode, score: u32) -> HighScore {
+        HighScore {
+            mode,
+            score,
+            levels_solved: score / 100,
+            date: DailyDate {
+                year: 2026,
+                month: 2,
+                day: 1,
+            },
+        }
+    }
+
+    fn scores(leaderboard: &Leaderboard, mode: GameMode) -> Vec<u32> {
+        leaderboard.top(mode).map(|score| score.score).collect()
+    }
+
+    #[test]
+    fn scores_are_ranked_highest_first() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(
+            leaderboard.insert(high_score(GameMode::Campaign, 300)),
+            Some(0)
+        );
+        assert_eq!(
+            leaderboard.insert(high_score(GameMode::Campaign, 500)),
+            Some(0)
+        );
+        assert_eq!(
+            leaderboard.insert(high_score(GameMode::Endless, 400)),
+            Some(0)
+        );
+        // NOTE: ties rank after the scores that were there first
+        assert_eq!(
+            leaderboard.insert(high_score(GameMode::Campaign, 300)),
+            Some(2)
+        );
+        assert_eq!(
+            leaderboard.insert(high_score(GameMode::Campaign, 400)),
+            Some(1)
+        );
+
+        assert_eq!(
+            scores(&leaderboard, GameMode::Campaign),
+            [500, 400, 300, 300]
+        );
+        assert_eq!(scores(&leaderboard, GameMode::Endless), [400]);
+    }
+
+    #[test]
+    fn only_the_best_scores_of_each_mode_are_kept() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.insert(high_score(GameMode::Daily, 50));
+        for score in 1..=HIGH_SCORES_PER_MODE as u32 {
+            leaderboard.insert(high_score(GameMode::Campaign, score * 100));
+        }
+
+        assert_eq!(
+            leaderboard.insert(high_score(GameMode::Campaign, 100)),
+            None
+        );
+        assert_eq!(
+            leaderboard.insert(high_score(GameMode::Campaign, 150)),
+            Some(9)
+        );
+
+        let campaign = scores(&leaderboard, GameMode::Campaign);
+        assert_eq!(campaign.len(), HIGH_SCORES_PER_MODE);
+        assert_eq!(campaign.first(), Some(&1000));
+        assert_eq!(campaign.last(), Some(&150));
+        assert_eq!(scores(&leaderboard, GameMode::Daily), [50]);
+    }
+}
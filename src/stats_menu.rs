@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::GameMode,
+    input::{Action, ActionState},
+    level_pack::{ActiveLevelPack, LevelPack},
+    stats::{Leaderboard, LevelStats},
+    widgets::{spawn_game_button, GameButton},
+    AppState,
+};
+
+/// Number of high scores shown for every game mode.
+const SHOWN_HIGH_SCORES: usize = 5;
+
+/// Game modes in the order their high scores are shown.
+const MODES: [GameMode; 5] = [
+    GameMode::Campaign,
+    GameMode::Timed,
+    GameMode::Reverse,
+    GameMode::Endless,
+    GameMode::Daily,
+];
+
+/// Statistics screen, pushed over the main menu.
+pub struct StatsMenuPlugin;
+
+impl Plugin for StatsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Stats).with_system(setup),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Stats).with_system(teardown),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Stats).with_system(back),
+        );
+    }
+}
+
+#[derive(Component)]
+struct StatsMenu;
+
+#[derive(Component)]
+struct BackButton;
+
+fn mode_name(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Campaign => "Campaign",
+        GameMode::Endless => "Endless",
+        GameMode::Daily => "Daily",
+        GameMode::Timed => "Timed",
+        GameMode::Reverse => "Reverse",
+    }
+}
+
+fn high_scores_text(leaderboard: &Leaderboard) -> String {
+    MODES
+        .iter()
+        .map(|mode| {
+            let scores: Vec<String> = leaderboard
+                .top(*mode)
+                .take(SHOWN_HIGH_SCORES)
+                .enumerate()
+                .map(|(rank, score)| {
+                    format!(
+                        "{}. {} pts, {} levels ({})",
+                        rank + 1,
+                        score.score,
+                        score.levels_solved,
+                        score.date
+                    )
+                })
+                .collect();
+
+            match scores.is_empty() {
+                true => format!("{}: no runs yet", mode_name(*mode)),
+                false => {
+                    format!("{}:\n{}", mode_name(*mode), scores.join("\n"))
+                }
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn level_stats_text(level_stats: &LevelStats, pack: &LevelPack) -> String {
+    (0..pack.level_count())
+        .map(|level_index| {
+            let record = level_stats.record(&pack.name, level_index);
+            match record {
+                Some(record) => format!(
+                    "Level {}: {} attempts, {} first try, best {}",
+                    level_index + 1,
+                    record.attempts,
+                    record.first_try_solves,
+                    record.best_time.map_or("-".to_string(), |time| {
+                        format!("{:.1}s", time)
+                    })
+                ),
+                None => format!("Level {}: not played", level_index + 1),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    leaderboard: Res<Leaderboard>,
+    level_stats: Res<LevelStats>,
+    active_pack: Res<ActiveLevelPack>,
+    packs: Res<Assets<LevelPack>>,
+) {
+    let back_button = spawn_game_button(
+        &mut commands,
+        &asset_server,
+        GameButton {
+            text: "Back".into(),
+        },
+    );
+    commands.entity(back_button).insert(BackButton);
+
+    let text_style = TextStyle {
+        font: asset_server.load("edosz.ttf"),
+        font_size: 20.0,
+        color: Color::YELLOW_GREEN,
+    };
+    let levels_text = packs
+        .get(&active_pack.0)
+        .map_or("Levels are still loading".to_string(), |pack| {
+            level_stats_text(&level_stats, pack)
+        });
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(StatsMenu)
+        .with_children(|main_container| {
+            main_container.spawn_bundle(TextBundle::from_section(
+                "Statistics",
+                TextStyle {
+                    font: asset_server.load("edosz.ttf"),
+                    font_size: 45.0,
+                    color: Color::GREEN,
+                },
+            ));
+
+            main_container
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    style: Style {
+                        size: Size::new(
+                            Val::Percent(100.0),
+                            Val::Percent(80.0),
+                        ),
+                        justify_content: JustifyContent::SpaceAround,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|columns| {
+                    columns.spawn_bundle(TextBundle::from_section(
+                        high_scores_text(&leaderboard),
+                        text_style.clone(),
+                    ));
+                    columns.spawn_bundle(TextBundle::from_section(
+                        levels_text,
+                        text_style,
+                    ));
+                });
+        })
+        .add_child(back_button);
+}
+
+fn teardown(mut commands: Commands, query: Query<Entity, With<StatsMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn back(
+    mut app_state: ResMut<State<AppState>>,
+    mut actions: ResMut<ActionState>,
+    query: Query<&Interaction, (With<BackButton>, Changed<Interaction>)>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+
+    if clicked || actions.just_pressed(Action::Menu) {
+        actions.consume(Action::Menu);
+        app_state.pop().unwrap();
+    }
+}
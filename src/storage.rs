@@ -0,0 +1,84 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Keeps small pieces of data, like settings, between runs.
+///
+/// Every launcher provides the implementation that fits its platform by
+/// inserting a [`StorageBackend`] before the app runs. Without one, nothing is
+/// persisted.
+pub trait Storage: Send + Sync + 'static {
+    /// Returns the value saved under `key`, if any.
+    fn read(&self, key: &str) -> Option<String>;
+
+    fn write(&self, key: &str, value: &str) -> std::io::Result<()>;
+
+    fn remove(&self, key: &str) -> std::io::Result<()>;
+}
+
+/// Keeps data for as long as the game runs, for platforms where nothing can
+/// be persisted.
+#[derive(Default)]
+pub struct MemoryStorage {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        self.values.lock().ok()?.get(key).cloned()
+    }
+
+    fn write(&self, key: &str, value: &str) -> std::io::Result<()> {
+        if let Ok(mut values) = self.values.lock() {
+            values.insert(key.to_string(), value.to_string());
+        }
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        if let Ok(mut values) = self.values.lock() {
+            values.remove(key);
+        }
+        Ok(())
+    }
+}
+
+pub struct StorageBackend(pub Box<dyn Storage>);
+
+impl StorageBackend {
+    pub fn new(storage: impl Storage) -> Self {
+        Self(Box::new(storage))
+    }
+
+    /// Reads and parses the value saved under `key`. Values that can't be
+    /// parsed are ignored, as if nothing was saved.
+    pub fn load<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.0.read(key)?;
+        match ron::from_str(&value) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!("Ignoring unreadable saved data for {}: {}", key, err);
+                None
+            }
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, key: &str, value: &T) {
+        let result = ron::to_string(value)
+            .map_err(|err| err.to_string())
+            .and_then(|value| {
+                self.0.write(key, &value).map_err(|err| err.to_string())
+            });
+
+        if let Err(err) = result {
+            warn!("Failed to save {}: {}", key, err);
+        }
+    }
+
+    pub fn remove(&self, key: &str) {
+        if let Err(err) = self.0.remove(key) {
+            warn!("Failed to remove {}: {}", key, err);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use bevy::{prelude::*, ui::UiSystem};
+
+/// Lets focusable buttons be navigated and pressed with a gamepad.
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            // NOTE: presses are made by setting the button's interaction, so
+            // they must happen after the UI updates it from the mouse.
+            press_focused.after(UiSystem::Focus),
+        )
+        .add_system(navigate_focus);
+    }
+}
+
+// contains properties accessible from the outside
+#[derive(Component, Default)]
+pub struct Focusable {
+    pub focused: bool,
+}
+
+const DIRECTIONS: [(GamepadButtonType, Vec2); 4] = [
+    (GamepadButtonType::DPadUp, Vec2::Y),
+    (GamepadButtonType::DPadDown, Vec2::NEG_Y),
+    (GamepadButtonType::DPadLeft, Vec2::NEG_X),
+    (GamepadButtonType::DPadRight, Vec2::X),
+];
+
+fn just_pressed(
+    gamepads: &Gamepads,
+    buttons: &Input<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    gamepads.iter().any(|gamepad| {
+        buttons.just_pressed(GamepadButton::new(*gamepad, button_type))
+    })
+}
+
+/// Moves the focus to the closest visible focusable in the direction pressed
+/// on the D-pad, or to the top-left one if nothing is focused yet.
+fn navigate_focus(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    mut query: Query<(Entity, &mut Focusable, &Node, &GlobalTransform)>,
+) {
+    let direction = DIRECTIONS
+        .iter()
+        .find(|(button_type, _)| {
+            just_pressed(&gamepads, &buttons, *button_type)
+        })
+        .map(|(_, direction)| *direction);
+    let direction = match direction {
+        Some(direction) => direction,
+        None => return,
+    };
+
+    // NOTE: hidden nodes are laid out with no size
+    let visible = |node: &Node| node.size != Vec2::ZERO;
+    let current = query
+        .iter()
+        .find(|(_, focusable, _, _)| focusable.focused)
+        .map(|(entity, _, _, transform)| {
+            (entity, transform.translation().truncate())
+        });
+
+    let target = match current {
+        Some((current, position)) => query
+            .iter()
+            .filter(|(entity, _, node, _)| *entity != current && visible(node))
+            .filter_map(|(entity, _, _, transform)| {
+                let offset = transform.translation().truncate() - position;
+                let along = offset.dot(direction);
+                let across = offset.perp_dot(direction).abs();
+                // NOTE: favor nodes that are in line with the current one
+                (along > 0.0).then_some((entity, along + 2.0 * across))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity),
+        None => query
+            .iter()
+            .filter(|(_, _, node, _)| visible(node))
+            .map(|(entity, _, _, transform)| {
+                let position = transform.translation();
+                (entity, position.x - position.y)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity),
+    };
+
+    if let Some(target) = target {
+        for (entity, mut focusable, _, _) in query.iter_mut() {
+            let focused = entity == target;
+            if focusable.focused != focused {
+                focusable.focused = focused;
+            }
+        }
+    }
+}
+
+/// Clicks the focused button when the gamepad's south button (A on Xbox
+/// controllers) is pressed, and releases it on the next frame.
+fn press_focused(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    mut pressed: Local<Option<Entity>>,
+    mut query: Query<(Entity, &Focusable, &mut Interaction)>,
+) {
+    if let Some(entity) = pressed.take() {
+        if let Ok((_, _, mut interaction)) = query.get_mut(entity) {
+            if *interaction == Interaction::Clicked {
+                *interaction = Interaction::None;
+            }
+        }
+    }
+
+    if !just_pressed(&gamepads, &buttons, GamepadButtonType::South) {
+        return;
+    }
+
+    for (entity, focusable, mut interaction) in query.iter_mut() {
+        if focusable.focused {
+            *interaction = Interaction::Clicked;
+            *pressed = Some(entity);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
-use crate::game::GameOptions;
+use super::Focusable;
+use crate::settings::{play_effect, Settings};
 
 pub struct GameButtonPlugin;
 
@@ -64,6 +65,7 @@ pub fn spawn_game_button<'a>(
             ..default()
         })
         .insert(game_button)
+        .insert(Focusable::default())
         .insert(State { text_node })
         .add_child(text_node)
         .id()
@@ -80,23 +82,29 @@ fn update_text(
     }
 }
 
+// NOTE: focused buttons are highlighted the same way as hovered ones, so the
+// query watches both.
+#[allow(clippy::type_complexity)]
 fn handle_button_hover(
-    button_q: Query<(&State, &Interaction), Changed<Interaction>>,
+    button_q: Query<
+        (&State, &Interaction, &Focusable),
+        Or<(Changed<Interaction>, Changed<Focusable>)>,
+    >,
     mut text_q: Query<&mut Text, With<GameButtonText>>,
 ) {
-    for (state, interaction) in button_q.iter() {
+    for (state, interaction, focusable) in button_q.iter() {
         if let Ok(mut text) = text_q.get_mut(state.text_node) {
-            match interaction {
-                Interaction::Clicked => (),
-                Interaction::Hovered => {
+            match (interaction, focusable.focused) {
+                (Interaction::Clicked, _) => (),
+                (Interaction::Hovered, _) | (Interaction::None, true) => {
                     text.sections.iter_mut().for_each(|s| {
-                        s.style.color = Color::WHITE.into();
+                        s.style.color = Color::WHITE;
                         s.style.font_size = 35.0;
                     });
                 }
-                Interaction::None => {
+                (Interaction::None, false) => {
                     text.sections.iter_mut().for_each(|s| {
-                        s.style.color = Color::BLACK.into();
+                        s.style.color = Color::BLACK;
                         s.style.font_size = 30.0;
                     });
                 }
@@ -109,13 +117,12 @@ fn click_sound(
     query: Query<&Interaction, (With<GameButton>, Changed<Interaction>)>,
     asset_server: Res<AssetServer>,
     audio: Res<Audio>,
-    options: Res<GameOptions>,
+    settings: Res<Settings>,
 ) {
-    if options.mute {
-        return;
-    }
-    
-    if query.iter().any(|interaction| *interaction == Interaction::Clicked) {
-        audio.play(asset_server.load("audio/click2.ogg"));
+    if query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked)
+    {
+        play_effect(&audio, &asset_server, &settings, "audio/click2.ogg");
     }
 }
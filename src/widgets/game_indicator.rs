@@ -41,7 +41,7 @@ pub fn spawn_game_indicator<'a>(
     let text_node = commands
         .spawn_bundle(TextBundle::from_sections([
             TextSection {
-                value: format!("{}: ", indicator.label).into(),
+                value: format!("{}: ", indicator.label),
                 style: TextStyle {
                     font: asset_server.load("edosz.ttf"),
                     font_size: 24.0,
@@ -77,7 +77,7 @@ fn update_text(
 ) {
     for (indicator, state) in button_q.iter() {
         if let Ok(mut text) = text_q.get_mut(state.text_node) {
-            text.sections[0].value = format!("{}: ", indicator.label).into();
+            text.sections[0].value = format!("{}: ", indicator.label);
             text.sections[1].value = indicator.value.clone();
         }
     }
@@ -1,5 +1,7 @@
+mod focus;
 mod game_button;
 mod game_indicator;
 
+pub use focus::*;
 pub use game_button::*;
 pub use game_indicator::*;